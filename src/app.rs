@@ -1,17 +1,31 @@
 use anyhow::{Result, bail};
 use ratatui::{Terminal, backend::Backend, crossterm::event::KeyEvent};
-use std::sync::mpsc as std_chan;
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self as std_chan, RecvTimeoutError},
+    time::{Duration, Instant},
+};
 
 use crate::{
     config::Config,
+    constants,
     event_handler::{self, Event},
     model::{
+        command::CommandLine,
         connection::{Connection, MusingRequest},
         cover_art::CoverArtState,
-        keybind::Keybind,
-        library::LibraryState,
-        musing::MusingState,
+        files::FilesState,
+        global_search::GlobalSearchState,
+        help::HelpState,
+        keybind::Keymap,
+        library::{LibraryState, LibraryView},
+        macros::{MacroState, Macros},
+        mouse::MouseState,
+        musing::{MusingState, PlaybackState},
         queue::QueueState,
+        search::{SearchContext, SearchHistory, SearchMode},
+        song_info::SongInfoState,
+        stats::StatsState,
         theme::Theme,
     },
     update, view,
@@ -30,28 +44,56 @@ pub enum Screen {
     Cover,
     Queue,
     Library,
+    Files,
+    Stats,
+    Search,
+}
+
+// shown on top of the current screen
+#[derive(Debug)]
+pub enum Popup {
+    SongInfo(SongInfoState),
+    Help(HelpState),
 }
 
 #[derive(Debug)]
 pub struct AppConfig {
     pub theme: Theme,
-    pub keybind: Keybind,
+    pub keybind: Keymap,
+    pub macros: Macros,
     pub seek_step: i64,
     pub volume_step: i8,
+    pub volume_presets: HashMap<String, u64>,
     pub speed_step: i16,
+    pub search_filter: bool,
+    pub search_mode: SearchMode,
+    pub search_case_sensitive: bool,
+    pub key_timeout: Option<Duration>, // for half-typed key sequences
 }
 
 pub struct App {
     pub connection: Connection,
     pub app_state: AppState,
     pub screen: Screen,
+    pub popup: Option<Popup>,
     pub musing_state: MusingState,
     pub queue_state: QueueState,
     pub library_state: LibraryState,
+    pub files_state: FilesState,
+    pub stats_state: StatsState,
+    pub global_search_state: GlobalSearchState,
     pub cover_art_state: CoverArtState,
+    pub mouse_state: MouseState,
     pub key_events: Vec<KeyEvent>,
+    pub count: Option<usize>, // typed before a binding to repeat it
     pub status_msg: Option<String>,
     pub searching: bool,
+    pub command_line: CommandLine,
+    pub macro_state: MacroState,
+    pub search_history: HashMap<SearchContext, SearchHistory>,
+    pub play_when_queued: Option<String>, // a song to be played as soon as it's in the queue
+    pub unmuted_volume: Option<u64>,      // Some while muted, restored when unmuting
+    pub dirty: bool,                      // whether the screen has to be redrawn
     pub config: AppConfig,
     tx: std_chan::Sender<Event>,
     rx: std_chan::Receiver<Event>,
@@ -63,42 +105,87 @@ impl App {
             port,
             theme,
             keybind,
+            macros,
             seek_step,
             volume_step,
+            volume_presets,
             speed_step,
+            search_filter,
+            search_mode,
+            search_case_sensitive,
+            mouse: _,
+            key_timeout,
             library_group_by,
+            library_views,
+            library_sort,
             queue_tags,
         } = config;
         let (tx, rx) = std_chan::channel();
         let connection = Connection::try_new(port, tx.clone())?;
         let app_state = AppState::default();
         let screen = Screen::default();
+        let popup = None;
         let musing_state = MusingState::default();
         let queue_state = QueueState::new(queue_tags);
-        let library_state = LibraryState::new(library_group_by);
+        // the view defined by `library_group_by` always comes first
+        let mut views = vec![LibraryView::new(
+            constants::DEFAULT_VIEW_NAME,
+            library_group_by,
+        )];
+        views.extend(library_views);
+        let library_state = LibraryState::new(views, library_sort);
+        let files_state = FilesState::default();
+        let stats_state = StatsState::default();
+        let global_search_state = GlobalSearchState::default();
         let cover_art_state = CoverArtState::try_new(tx.clone())?;
+        let mouse_state = MouseState::default();
         let key_events = Vec::new();
+        let count = None;
         let status_msg = None;
         let searching = false;
+        let command_line = CommandLine::default();
+        let macro_state = MacroState::default();
+        let search_history = HashMap::new();
+        let play_when_queued = None;
+        let unmuted_volume = None;
+        let dirty = true;
         let config = AppConfig {
             theme,
             keybind,
+            macros,
             seek_step,
             volume_step,
+            volume_presets,
             speed_step,
+            search_filter,
+            search_mode,
+            search_case_sensitive,
+            key_timeout: (key_timeout > 0).then(|| Duration::from_millis(key_timeout)),
         };
 
         Ok(Self {
             connection,
             app_state,
             screen,
+            popup,
             musing_state,
             queue_state,
             library_state,
+            files_state,
+            stats_state,
+            global_search_state,
             cover_art_state,
+            mouse_state,
             key_events,
+            count,
             status_msg,
             searching,
+            command_line,
+            macro_state,
+            search_history,
+            play_when_queued,
+            unmuted_volume,
+            dirty,
             config,
             tx,
             rx,
@@ -107,26 +194,103 @@ impl App {
 
     pub fn run(&mut self, terminal: &mut Terminal<impl Backend>) -> Result<()> {
         update::update_library(self);
-        event_handler::run(self.tx.clone());
+        let tx_interval = event_handler::run(self.tx.clone());
+        let mut interval = Some(event_handler::REFRESH_PLAYING);
+        let mut was_pending = false;
+        let mut focused = true;
+        let mut last_keypress = Instant::now();
 
         loop {
-            // we get events from 4 sources: key presses, the automatic refresh, cover art
-            // resizing and the network connection
-            match self.rx.recv() {
-                Ok(event) => match event {
-                    Event::Keypress(ev) => {
-                        if let Some(msg) = update::translate_key_event(self, ev) {
-                            let _ = self.status_msg.take();
-                            update::update_on_message(self, msg);
-                        }
-                    }
-                    Event::CoverArtResize(redraw) => update::update_cover_art(self, redraw?),
-                    Event::MusingResponse(response) => update::update_on_response(self, response),
-                    Event::Refresh => self.connection.send(MusingRequest::StateDelta),
+            // while a search is computing, wake up now and then to show its results
+            // (and once more right after it's done)
+            let pending = update::search_is_pending(self);
+            // a half-typed key sequence is forgotten after a while
+            let key_time_left = match self.config.key_timeout {
+                Some(timeout) if !self.key_events.is_empty() => {
+                    Some(timeout.saturating_sub(last_keypress.elapsed()))
+                }
+                _ => None,
+            };
+            let timeout = match pending {
+                true => Some(Duration::from_millis(event_handler::SEARCH_POLL)),
+                false => None,
+            };
+            let timeout = timeout.into_iter().chain(key_time_left).min();
+            let event = match (timeout, was_pending && !pending) {
+                (_, true) => None,
+                (Some(timeout), false) => match self.rx.recv_timeout(timeout) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => bail!("event handler crashed"),
+                },
+                (None, false) => match self.rx.recv() {
+                    Ok(event) => Some(event),
+                    Err(_) => bail!("event handler crashed"),
                 },
-                Err(_) => bail!("event handler crashed"),
+            };
+            was_pending = pending;
+            // we get events from 4 sources: the terminal (key presses, mouse, paste, focus changes,
+            // resizes), the automatic refresh, cover art resizing and the network connection
+            match event {
+                Some(Event::Keypress(ev)) => {
+                    self.dirty = true;
+                    last_keypress = Instant::now();
+                    if let Some(msg) = update::translate_key_event(self, ev) {
+                        let _ = self.status_msg.take();
+                        update::update_on_message(self, msg);
+                    }
+                }
+                Some(Event::Paste(text)) => {
+                    if let Some(msg) = update::translate_paste(self, &text) {
+                        self.dirty = true;
+                        update::update_on_message(self, msg);
+                    }
+                }
+                Some(Event::Mouse(ev)) => {
+                    if let Some(msg) = update::translate_mouse_event(self, ev) {
+                        self.dirty = true;
+                        update::update_on_message(self, msg);
+                    }
+                }
+                Some(Event::Focus(gained)) => {
+                    focused = gained;
+                    if gained {
+                        // catch up on what happened in the meantime
+                        self.connection.send(MusingRequest::StateDelta);
+                    }
+                }
+                Some(Event::Resize) => self.dirty = true,
+                Some(Event::CoverArtResize(redraw)) => {
+                    self.dirty = true;
+                    update::update_cover_art(self, redraw?);
+                }
+                Some(Event::MusingResponse(response)) => update::update_on_response(self, response),
+                Some(Event::Refresh) => self.connection.send(MusingRequest::StateDelta),
+                None => {
+                    self.dirty = true;
+                    if let Some(timeout) = self.config.key_timeout
+                        && last_keypress.elapsed() >= timeout
+                    {
+                        self.key_events.clear();
+                        self.count = None;
+                    }
+                }
+            }
+            update::clamp_selection(self);
+            // refresh often only when the timer is running, and not at all when nobody's looking
+            let new_interval = match self.musing_state.playback_state {
+                _ if !focused => None,
+                PlaybackState::Playing => Some(event_handler::REFRESH_PLAYING),
+                PlaybackState::Paused | PlaybackState::Stopped => Some(event_handler::REFRESH_IDLE),
+            };
+            if new_interval != interval {
+                interval = new_interval;
+                let _ = tx_interval.send(interval.map(Duration::from_millis));
+            }
+            if self.dirty {
+                terminal.draw(|frame| view::render(self, frame))?;
+                self.dirty = false;
             }
-            terminal.draw(|frame| view::render(self, frame))?;
             if let AppState::Done = self.app_state {
                 break;
             }
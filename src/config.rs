@@ -1,14 +1,24 @@
 use anyhow::{Result, anyhow, bail};
 use clap::Parser;
 use std::{
+    collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 use toml::{Table, Value as TomlValue};
 
 use crate::{
     constants,
-    model::{keybind::Keybind, theme::Theme},
+    model::{
+        collation::Collation,
+        command::Command,
+        keybind::{Binding, Keymap},
+        library::LibraryView,
+        macros::Macros,
+        search::SearchMode,
+        theme::Theme,
+    },
 };
 
 #[derive(Parser, Debug)]
@@ -22,11 +32,20 @@ pub struct CliOptions {
 pub struct Config {
     pub port: u16,
     pub theme: Theme,
-    pub keybind: Keybind,
+    pub keybind: Keymap,
+    pub macros: Macros,
     pub seek_step: i64,
     pub volume_step: i8,
+    pub volume_presets: HashMap<String, u64>,
     pub speed_step: i16,
+    pub search_filter: bool,
+    pub search_mode: SearchMode,
+    pub search_case_sensitive: bool,
+    pub mouse: bool,
+    pub key_timeout: u64, // in ms, 0 means no timeout
     pub library_group_by: Vec<String>,
+    pub library_views: Vec<LibraryView>,
+    pub library_sort: Collation,
     pub queue_tags: Vec<String>,
 }
 
@@ -35,14 +54,23 @@ impl Default for Config {
         Self {
             port: constants::DEFAULT_PORT,
             theme: Theme::default(),
-            keybind: Keybind::default(),
+            keybind: Keymap::default(),
+            macros: Macros::default(),
             seek_step: constants::DEFAULT_SEEK_STEP,
             volume_step: constants::DEFAULT_VOLUME_STEP,
+            volume_presets: HashMap::new(),
             speed_step: constants::DEFAULT_SPEED_STEP,
+            search_filter: constants::DEFAULT_SEARCH_FILTER,
+            search_mode: SearchMode::default(),
+            search_case_sensitive: constants::DEFAULT_SEARCH_CASE_SENSITIVE,
+            mouse: constants::DEFAULT_MOUSE,
+            key_timeout: constants::DEFAULT_KEY_TIMEOUT,
             library_group_by: constants::DEFAULT_GROUP_BY
                 .iter()
                 .map(|s| s.to_string())
                 .collect(),
+            library_views: Vec::new(),
+            library_sort: Collation::default(),
             queue_tags: constants::DEFAULT_QUEUE_TAGS
                 .iter()
                 .map(|s| s.to_string())
@@ -71,7 +99,10 @@ impl Config {
                     config.theme = Theme::try_from(theme)?;
                 }
                 ("keybind", TomlValue::Table(keybind)) => {
-                    config.keybind = Keybind::try_from(keybind)?;
+                    config.keybind = Keymap::try_from(keybind)?;
+                }
+                ("macros", TomlValue::Table(macros)) => {
+                    config.macros = Macros::try_from(macros)?;
                 }
                 ("seek_step", TomlValue::Integer(seek_step)) => {
                     config.seek_step = seek_step;
@@ -79,14 +110,65 @@ impl Config {
                 ("volume_step", TomlValue::Integer(volume_step)) => {
                     config.volume_step = i8::try_from(volume_step)?;
                 }
+                ("volume_presets", TomlValue::Table(volume_presets)) => {
+                    config.volume_presets = volume_presets
+                        .into_iter()
+                        .map(|(name, val)| match val.as_integer() {
+                            Some(volume @ 0..=100) => Ok((name, volume as u64)),
+                            _ => bail!("volume preset `{}` should be between 0 and 100", name),
+                        })
+                        .collect::<Result<_>>()?;
+                }
                 ("speed_step", TomlValue::Integer(speed_step)) => {
                     config.speed_step = i16::try_from(speed_step)?;
                 }
+                ("search_filter", TomlValue::Boolean(search_filter)) => {
+                    config.search_filter = search_filter;
+                }
+                ("search_mode", TomlValue::String(search_mode)) => {
+                    config.search_mode = SearchMode::from_str(&search_mode)
+                        .map_err(|_| anyhow!("invalid search mode `{}`", search_mode))?;
+                }
+                ("search_case_sensitive", TomlValue::Boolean(search_case_sensitive)) => {
+                    config.search_case_sensitive = search_case_sensitive;
+                }
+                ("mouse", TomlValue::Boolean(mouse)) => config.mouse = mouse,
+                ("key_timeout", TomlValue::Integer(key_timeout)) => {
+                    config.key_timeout = u64::try_from(key_timeout)?;
+                }
                 ("library_group_by", TomlValue::Array(library_group_by)) => {
                     config.library_group_by = library_group_by
                         .iter()
                         .filter_map(|s| s.as_str().map(|s| s.to_string()))
                         .collect();
+                    if config.library_group_by.is_empty() {
+                        bail!("`library_group_by` can't be empty");
+                    }
+                }
+                ("library_views", TomlValue::Array(library_views)) => {
+                    config.library_views = library_views
+                        .into_iter()
+                        .map(|view| match view {
+                            TomlValue::Table(view) => LibraryView::try_from(view),
+                            _ => bail!("expected a table"),
+                        })
+                        .collect::<Result<_>>()?;
+                    // responses from musing find their view by its name
+                    let mut names = HashSet::new();
+                    for view in config.library_views.iter() {
+                        if view.name == constants::DEFAULT_VIEW_NAME {
+                            bail!(
+                                "`{}` is the name of the view defined by `library_group_by`",
+                                view.name
+                            );
+                        }
+                        if !names.insert(view.name.as_str()) {
+                            bail!("there's more than one library view named `{}`", view.name);
+                        }
+                    }
+                }
+                ("library_sort", TomlValue::Table(library_sort)) => {
+                    config.library_sort = Collation::try_from(library_sort)?;
                 }
                 ("queue_tags", TomlValue::Array(queue_tags)) => {
                     config.queue_tags = queue_tags
@@ -97,7 +179,29 @@ impl Config {
                 (other, _) => bail!("invalid config key `{}`", other),
             }
         }
+        // the names of macros and presets can only be checked once the whole config is read
+        for (_, binding, _) in config.keybind.bindings() {
+            match binding {
+                Binding::Macro(name) if !config.macros.contains(&name) => {
+                    bail!("unknown macro `{}`", name);
+                }
+                Binding::Command(line) => config.check_command(&Command::from_str(&line)?)?,
+                _ => (),
+            }
+        }
+        for command in config.macros.commands() {
+            config.check_command(command)?;
+        }
 
         Ok(config)
     }
+
+    fn check_command(&self, command: &Command) -> Result<()> {
+        match command {
+            Command::VolumePreset(name) if !self.volume_presets.contains_key(name) => {
+                bail!("unknown volume preset `{}`", name)
+            }
+            _ => Ok(()),
+        }
+    }
 }
@@ -2,9 +2,39 @@ pub const DEFAULT_PORT: u16 = 2137;
 pub const DEFAULT_SEEK_STEP: i64 = 5;
 pub const DEFAULT_VOLUME_STEP: i8 = 5;
 pub const DEFAULT_SPEED_STEP: i16 = 5;
+pub const DEFAULT_SEARCH_FILTER: bool = true;
+pub const DEFAULT_SEARCH_CASE_SENSITIVE: bool = false;
+pub const DEFAULT_MOUSE: bool = true;
+// how long (in ms) a half-typed key sequence waits for the next key, 0 means forever
+pub const DEFAULT_KEY_TIMEOUT: u64 = 0;
+// the longest time between the two clicks of a double click
+pub const DOUBLE_CLICK_MS: u64 = 400;
+// the largest count that can be typed before a binding (e.g. 10j)
+pub const MAX_COUNT: usize = 9999;
+pub const SEARCH_HISTORY_LEN: usize = 100;
+// how long the search waits for more keystrokes before computing the results
+pub const SEARCH_DEBOUNCE_MS: u64 = 25;
 pub const DEFAULT_GROUP_BY: [&str; 2] = ["albumartist", "album"];
+// tags fetched for every song in the library
+pub const CHILDREN_TAGS: [&str; 8] = [
+    "tracknumber",
+    "tracktitle",
+    "artist",
+    "albumartist",
+    "album",
+    "genre",
+    "date",
+    "duration",
+];
+pub const DEFAULT_SORT_BY: [&str; 2] = ["tracknumber", "tracktitle"];
+pub const DEFAULT_VIEW_NAME: &str = "default";
+pub const DEFAULT_IGNORED_PREFIXES: [&str; 2] = ["The ", "A "];
+// tags that may have a "<tag>sort" counterpart (e.g. albumartistsort)
+pub const SORTABLE_TAGS: [&str; 4] = ["album", "albumartist", "artist", "composer"];
 pub const DEFAULT_QUEUE_TAGS: [&str; 3] = ["tracktitle", "artist", "album"];
 pub const DEFAULT_CONFIG_DIR: &str = "amusing";
 pub const DEFAULT_CONFIG_FILE: &str = "amusing.toml";
+// inside of <data_dir>/amusing
+pub const PLAYLIST_DIR: &str = "playlists";
 pub const DEFAULT_BACKTRACE_FILE: &str = "amusing.backtrace";
 pub const UNKNOWN: &str = "<unknown>";
@@ -2,43 +2,75 @@ use anyhow::Result;
 use ratatui::crossterm::event::{self, Event as TermEvent};
 use ratatui_image::{errors::Errors, thread::ResizeResponse};
 use std::{
-    sync::mpsc as std_chan,
+    sync::mpsc::{self as std_chan, RecvTimeoutError},
     thread,
-    time::{Duration, Instant},
+    time::Duration,
 };
 
 use crate::{model::connection::MusingResponse, panic};
 
 // in ms
-const REFRESH_TIMEOUT: u64 = 250;
-const POLL_TIMEOUT: u64 = 16;
+pub const REFRESH_PLAYING: u64 = 250;
+pub const REFRESH_IDLE: u64 = 1000;
+// how often the screen is redrawn while a search is computing its results
+pub const SEARCH_POLL: u64 = 16;
 
 pub enum Event {
     Keypress(event::KeyEvent),
+    Mouse(event::MouseEvent),
+    Paste(String),
+    Focus(bool), // gained or lost
+    Resize,
     CoverArtResize(Result<ResizeResponse, Errors>),
     MusingResponse(MusingResponse),
     Refresh,
 }
 
-pub fn run(tx_event: std_chan::Sender<Event>) {
-    let refresh_timer = Duration::from_millis(REFRESH_TIMEOUT);
-    let poll_timer = Duration::from_millis(POLL_TIMEOUT);
+// returns a channel for changing the interval between refreshes (None pauses them)
+pub fn run(tx_event: std_chan::Sender<Event>) -> std_chan::Sender<Option<Duration>> {
+    let tx_refresh = tx_event.clone();
     thread::spawn(move || {
         panic::register_backtrace_panic_handler();
-        let _ = tx_event.send(Event::Refresh);
-        let mut last_refresh = Instant::now();
+        // blocks until there's something to read, so no time is wasted on polling
         loop {
-            if event::poll(poll_timer).expect("event poll failed")
-                && let TermEvent::Key(ev) = event::read().expect("event read failed")
-                && let event::KeyEventKind::Press = ev.kind
-            {
-                let _ = tx_event.send(Event::Keypress(ev));
+            let event = match event::read().expect("event read failed") {
+                TermEvent::Key(ev) if ev.kind == event::KeyEventKind::Press => Event::Keypress(ev),
+                TermEvent::Mouse(ev) => Event::Mouse(ev),
+                TermEvent::Paste(text) => Event::Paste(text),
+                TermEvent::FocusGained => Event::Focus(true),
+                TermEvent::FocusLost => Event::Focus(false),
+                TermEvent::Resize(..) => Event::Resize,
+                _ => continue,
+            };
+            if tx_event.send(event).is_err() {
+                break;
             }
-            let now = Instant::now();
-            if now - last_refresh >= refresh_timer {
-                let _ = tx_event.send(Event::Refresh);
-                last_refresh = now;
+        }
+    });
+
+    let (tx_interval, rx_interval) = std_chan::channel();
+    thread::spawn(move || {
+        panic::register_backtrace_panic_handler();
+        let mut interval = Some(Duration::from_millis(REFRESH_PLAYING));
+        let _ = tx_refresh.send(Event::Refresh);
+        loop {
+            let msg = match interval {
+                Some(interval) => rx_interval.recv_timeout(interval),
+                None => rx_interval
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match msg {
+                Ok(new_interval) => interval = new_interval,
+                Err(RecvTimeoutError::Timeout) => {
+                    if tx_refresh.send(Event::Refresh).is_err() {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
             }
         }
     });
+
+    tx_interval
 }
@@ -5,6 +5,10 @@ use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
         ExecutableCommand,
+        event::{
+            DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+            EnableFocusChange, EnableMouseCapture,
+        },
         terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
     },
 };
@@ -26,13 +30,25 @@ use crate::{
 };
 
 fn run(config: Config) -> Result<()> {
+    let mouse = config.mouse;
     let mut app = App::try_new(config)?;
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
+    // so that pasting doesn't arrive as a flood of key presses
+    stdout().execute(EnableBracketedPaste)?;
+    stdout().execute(EnableFocusChange)?;
+    if mouse {
+        stdout().execute(EnableMouseCapture)?;
+    }
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
 
     let res = app.run(&mut terminal);
     disable_raw_mode()?;
+    if mouse {
+        stdout().execute(DisableMouseCapture)?;
+    }
+    stdout().execute(DisableFocusChange)?;
+    stdout().execute(DisableBracketedPaste)?;
     stdout().execute(LeaveAlternateScreen)?;
 
     res
@@ -1,9 +1,19 @@
+pub mod collation;
+pub mod command;
 pub mod common;
 pub mod connection;
 pub mod cover_art;
+pub mod files;
+pub mod global_search;
+pub mod help;
 pub mod keybind;
 pub mod library;
+pub mod macros;
+pub mod mouse;
 pub mod musing;
+pub mod query;
 pub mod queue;
 pub mod search;
+pub mod song_info;
+pub mod stats;
 pub mod theme;
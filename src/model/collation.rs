@@ -0,0 +1,300 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use toml::{Table, Value as TomlValue};
+
+use crate::constants;
+
+// how library groups are ordered
+#[derive(Debug)]
+pub struct Collation {
+    pub case_sensitive: bool,
+    pub natural: bool,   // compare runs of digits by their numeric value
+    pub unidecode: bool, // fold non-ASCII characters before comparing ("Ólafur" next to "Olivia")
+    pub ignored_prefixes: Vec<String>, // e.g. "The " so that "The Beatles" sorts under B
+    pub sort_tags: bool, // prefer e.g. `albumartistsort` over `albumartist` when present
+    pub albums_by_date: bool, // order albums by their date before their title
+}
+
+// pieces of a string, numbers are kept apart to compare them naturally ("2" < "10")
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Chunk {
+    Number(usize, String), // (count of significant digits, significant digits)
+    Text(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct SortField {
+    unknown: bool, // unknown values always go last
+    no_date: bool,
+    date: Vec<Chunk>,
+    value: Vec<Chunk>,
+}
+
+// the precomputed key by which a library group is sorted
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SortKey(Vec<SortField>);
+
+impl Default for Collation {
+    fn default() -> Self {
+        Self {
+            case_sensitive: false,
+            natural: true,
+            unidecode: true,
+            ignored_prefixes: constants::DEFAULT_IGNORED_PREFIXES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            sort_tags: true,
+            albums_by_date: false,
+        }
+    }
+}
+
+impl TryFrom<Table> for Collation {
+    type Error = anyhow::Error;
+
+    fn try_from(table: Table) -> Result<Self> {
+        let mut collation = Collation::default();
+        for (key, val) in table {
+            match (key.as_str(), val) {
+                ("case_sensitive", TomlValue::Boolean(case_sensitive)) => {
+                    collation.case_sensitive = case_sensitive;
+                }
+                ("natural", TomlValue::Boolean(natural)) => {
+                    collation.natural = natural;
+                }
+                ("unidecode", TomlValue::Boolean(unidecode)) => {
+                    collation.unidecode = unidecode;
+                }
+                ("ignored_prefixes", TomlValue::Array(ignored_prefixes)) => {
+                    collation.ignored_prefixes = ignored_prefixes
+                        .iter()
+                        .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                        .collect();
+                }
+                ("sort_tags", TomlValue::Boolean(sort_tags)) => {
+                    collation.sort_tags = sort_tags;
+                }
+                ("albums_by_date", TomlValue::Boolean(albums_by_date)) => {
+                    collation.albums_by_date = albums_by_date;
+                }
+                (other, _) => bail!("invalid config key `{}`", other),
+            }
+        }
+
+        Ok(collation)
+    }
+}
+
+impl Collation {
+    // tags that have to be fetched (in addition to the displayed ones) to sort the groups
+    pub fn extra_tags(&self, group_by: &[String]) -> Vec<String> {
+        let mut tags = Vec::new();
+        if self.sort_tags {
+            tags.extend(
+                group_by
+                    .iter()
+                    .filter(|tag| constants::SORTABLE_TAGS.contains(&tag.as_str()))
+                    .map(|tag| sort_tag(tag)),
+            );
+        }
+        if self.albums_by_date && group_by.iter().any(|tag| tag == "album") {
+            tags.push("date".into());
+        }
+
+        tags
+    }
+
+    // `first_song` is the metadata of any song of the group,
+    // it's where the values of sort tags and dates are taken from
+    pub fn sort_key(
+        &self,
+        group_by: &[String],
+        id_comb: &[String],
+        first_song: Option<&HashMap<String, String>>,
+    ) -> SortKey {
+        let fields = group_by
+            .iter()
+            .zip(id_comb.iter())
+            .map(|(tag, value)| {
+                let unknown = value == constants::UNKNOWN;
+                let value = first_song
+                    .filter(|_| self.sort_tags)
+                    .and_then(|m| m.get(&sort_tag(tag)))
+                    .unwrap_or(value);
+                let date = first_song
+                    .filter(|_| self.albums_by_date && tag == "album")
+                    .and_then(|m| m.get("date"));
+
+                SortField {
+                    unknown,
+                    no_date: date.is_none(),
+                    date: date.map(|d| self.chunks(d)).unwrap_or_default(),
+                    value: self.chunks(self.strip_prefix(value)),
+                }
+            })
+            .collect();
+
+        SortKey(fields)
+    }
+
+    fn strip_prefix<'a>(&self, s: &'a str) -> &'a str {
+        for prefix in self.ignored_prefixes.iter() {
+            if let Some(head) = s.get(..prefix.len())
+                && head.eq_ignore_ascii_case(prefix)
+                && s.len() > prefix.len()
+            {
+                return &s[prefix.len()..];
+            }
+        }
+
+        s
+    }
+
+    fn chunks(&self, s: &str) -> Vec<Chunk> {
+        let s = if self.unidecode {
+            unidecode::unidecode(s)
+        } else {
+            s.to_string()
+        };
+        let s = if self.case_sensitive {
+            s
+        } else {
+            s.to_lowercase()
+        };
+        if !self.natural {
+            return vec![Chunk::Text(s)];
+        }
+
+        let mut chunks = Vec::new();
+        let mut rest = s.as_str();
+        while let Some(c) = rest.chars().next() {
+            let is_digit = c.is_ascii_digit();
+            let end = rest
+                .find(|c: char| c.is_ascii_digit() != is_digit)
+                .unwrap_or(rest.len());
+            let (chunk, tail) = rest.split_at(end);
+            if is_digit {
+                let digits = chunk.trim_start_matches('0');
+                chunks.push(Chunk::Number(digits.len(), digits.to_string()));
+            } else {
+                chunks.push(Chunk::Text(chunk.to_string()));
+            }
+            rest = tail;
+        }
+
+        chunks
+    }
+}
+
+fn sort_tag(tag: &str) -> String {
+    format!("{}sort", tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the values of a single tag, in the order they're sorted in
+    fn sorted(collation: &Collation, values: &[&str]) -> Vec<String> {
+        let group_by = ["album".to_string()];
+        let mut values: Vec<_> = values.iter().map(|s| s.to_string()).collect();
+        values.sort_by_cached_key(|value| {
+            collation.sort_key(&group_by, std::slice::from_ref(value), None)
+        });
+
+        values
+    }
+
+    #[test]
+    fn natural_order() {
+        let collation = Collation::default();
+        assert_eq!(
+            sorted(
+                &collation,
+                &["Disc 10", "Disc 2", "disc 1", "Disc 02b", "Disc 007"]
+            ),
+            ["disc 1", "Disc 2", "Disc 02b", "Disc 007", "Disc 10"]
+        );
+        let collation = Collation {
+            natural: false,
+            ..Default::default()
+        };
+        assert_eq!(sorted(&collation, &["10", "2", "1"]), ["1", "10", "2"]);
+    }
+
+    #[test]
+    fn prefixes_and_case() {
+        let collation = Collation::default();
+        assert_eq!(
+            sorted(
+                &collation,
+                &[
+                    "The Beatles",
+                    "Abba",
+                    "a Perfect Circle",
+                    "Ólafur",
+                    "Olivia",
+                    "The"
+                ]
+            ),
+            [
+                "Abba",
+                "The Beatles",
+                "Ólafur",
+                "Olivia",
+                "a Perfect Circle",
+                "The"
+            ]
+        );
+        let collation = Collation {
+            case_sensitive: true,
+            ignored_prefixes: Vec::new(),
+            ..Default::default()
+        };
+        assert_eq!(
+            sorted(&collation, &["b", "The Beatles", "A"]),
+            ["A", "The Beatles", "b"]
+        );
+    }
+
+    #[test]
+    fn unknown_values_go_last() {
+        let collation = Collation::default();
+        assert_eq!(
+            sorted(&collation, &[constants::UNKNOWN, "zz top", "0"]),
+            ["0", "zz top", constants::UNKNOWN]
+        );
+    }
+
+    #[test]
+    fn sort_tags_and_dates() {
+        let collation = Collation {
+            albums_by_date: true,
+            ..Default::default()
+        };
+        let group_by = ["albumartist".to_string(), "album".to_string()];
+        let song = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(tag, value)| (tag.to_string(), value.to_string()))
+                .collect()
+        };
+        let key = |artist: &str, album: &str, song: &HashMap<String, String>| {
+            collation.sort_key(&group_by, &[artist.into(), album.into()], Some(song))
+        };
+        // `albumartistsort` decides, not the displayed value
+        assert!(
+            key("Zed", "x", &song(&[("albumartistsort", "Adams")])) < key("Bob", "x", &song(&[]))
+        );
+        // an older album goes first, and one without a date goes last
+        let old = key("Bob", "Zebra", &song(&[("date", "1999-01-01")]));
+        let new = key("Bob", "Apple", &song(&[("date", "2004")]));
+        let undated = key("Bob", "Aardvark", &song(&[]));
+        assert!(old < new && new < undated);
+        assert_eq!(
+            collation.extra_tags(&group_by),
+            ["albumartistsort", "albumsort", "date"]
+        );
+    }
+}
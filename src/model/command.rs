@@ -0,0 +1,348 @@
+use anyhow::{Result, anyhow, bail};
+use std::str::FromStr;
+use strum::VariantNames;
+use tui_input::Input;
+
+use crate::model::{keybind::Binding, search::SearchHistory};
+
+// commands that take an argument, all the others are named after bindings (e.g. `:next`)
+const ARG_COMMANDS: [&str; 7] = [
+    "add",
+    "mode",
+    "save-playlist",
+    "seek",
+    "speed",
+    "view",
+    "volume",
+];
+pub const MODES: [&str; 4] = ["gapless", "random", "sequential", "single"];
+
+#[derive(Clone, Copy, Debug)]
+pub enum Amount {
+    To(u64), // e.g. `:volume 40`
+    By(i64), // e.g. `:volume -10`
+}
+
+#[derive(Clone, Debug)]
+pub enum Command {
+    Binding(Binding),
+    Add(String),
+    SavePlaylist(String),
+    Seek(Amount),
+    SeekPercent(u64),
+    Speed(Amount),
+    View(String),
+    Volume(Amount),
+    VolumePreset(String), // e.g. `:volume night`
+}
+
+#[derive(Debug)]
+struct Completion {
+    prefix: String, // the part of the line that isn't being completed
+    candidates: Vec<String>,
+    i: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct CommandLine {
+    pub input: Option<Input>, // Some while the command line is open
+    pub history: SearchHistory,
+    completion: Option<Completion>,
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, arg) = s
+            .split_once(char::is_whitespace)
+            .map_or((s, ""), |(name, arg)| (name, arg.trim()));
+        let name = name.replace('_', "-");
+        if ARG_COMMANDS.contains(&name.as_str()) && arg.is_empty() {
+            bail!("`{}` needs an argument", name);
+        }
+        match name.as_str() {
+            "" => bail!("empty command"),
+            "add" => Ok(Command::Add(arg.to_string())),
+            "mode" => match arg {
+                "gapless" => Ok(Command::Binding(Binding::ModeGapless)),
+                "random" => Ok(Command::Binding(Binding::ModeRandom)),
+                "sequential" => Ok(Command::Binding(Binding::ModeSequential)),
+                "single" => Ok(Command::Binding(Binding::ModeSingle)),
+                _ => bail!(
+                    "invalid mode `{}` (expected one of {})",
+                    arg,
+                    MODES.join(", ")
+                ),
+            },
+            "save-playlist" => Ok(Command::SavePlaylist(arg.to_string())),
+            "seek" => match arg.strip_suffix('%') {
+                Some(percent) => percent
+                    .parse()
+                    .ok()
+                    .filter(|percent| *percent <= 100)
+                    .map(Command::SeekPercent)
+                    .ok_or(anyhow!("invalid percentage `{}`", arg)),
+                None => parse_amount(arg, parse_time).map(Command::Seek),
+            },
+            "speed" => parse_amount(arg, |s| s.parse().ok()).map(Command::Speed),
+            "view" => Ok(Command::View(arg.to_string())),
+            "volume" if arg.starts_with(char::is_alphabetic) => {
+                Ok(Command::VolumePreset(arg.to_string()))
+            }
+            "volume" => parse_amount(arg, |s| s.parse().ok()).map(Command::Volume),
+            _ => {
+                let binding = Binding::from_str(&name.replace('-', "_"))
+                    .ok()
+                    .filter(is_command)
+                    .ok_or(anyhow!("unknown command `{}`", name))?;
+                if !arg.is_empty() {
+                    bail!("`{}` doesn't take any arguments", name);
+                }
+
+                Ok(Command::Binding(binding))
+            }
+        }
+    }
+}
+
+impl CommandLine {
+    pub fn open(&mut self) {
+        self.input = Some(Input::default());
+        self.history.reset();
+        self.completion = None;
+    }
+
+    // returns what was typed
+    pub fn close(&mut self) -> Option<String> {
+        self.completion = None;
+        self.input.take().map(|input| input.value().to_string())
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.input.is_some()
+    }
+
+    pub fn value(&self) -> &str {
+        self.input.as_ref().map_or("", |input| input.value())
+    }
+
+    pub fn set_value(&mut self, value: String) {
+        if let Some(input) = self.input.as_mut() {
+            *input = Input::new(value);
+        }
+    }
+
+    // inserts `text` at the cursor in one step (e.g. when it's pasted)
+    pub fn insert(&mut self, text: &str) {
+        let Some(input) = self.input.as_mut() else {
+            return;
+        };
+        let cursor = input.cursor();
+        let value = input.value();
+        let split = value
+            .char_indices()
+            .nth(cursor)
+            .map_or(value.len(), |(i, _)| i);
+        let text = text.replace(['\r', '\n', '\t'], " ");
+        let new_value = format!("{}{}{}", &value[..split], text, &value[split..]);
+        *input = Input::new(new_value).with_cursor(cursor + text.chars().count());
+        self.completion = None;
+    }
+
+    // to be called whenever the line is edited by hand
+    pub fn edited(&mut self) {
+        self.completion = None;
+    }
+
+    pub fn recall(&mut self, delta: isize) {
+        let current = self.value().to_string();
+        let value = self.history.recall(delta, &current);
+        self.set_value(value);
+        self.completion = None;
+    }
+
+    // cycles through the possible completions of the word being typed,
+    // `args` gives the values that the argument of a command can have
+    pub fn complete(&mut self, delta: isize, args: impl FnOnce(&str, &str) -> Vec<String>) {
+        if let Some(completion) = self.completion.as_mut() {
+            let n = completion.candidates.len() as isize;
+            completion.i = (completion.i as isize + delta).rem_euclid(n) as usize;
+            let value = format!(
+                "{}{}",
+                completion.prefix, completion.candidates[completion.i]
+            );
+            self.set_value(value);
+            return;
+        }
+        let value = self.value().to_string();
+        let (prefix, word, candidates) = match value.split_once(' ') {
+            Some((name, arg)) => {
+                let arg = arg.trim_start();
+                let prefix = value[..value.len() - arg.len()].to_string();
+                (prefix, arg, args(&name.replace('_', "-"), arg))
+            }
+            None => (String::new(), value.as_str(), names()),
+        };
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+        let i = if delta < 0 { candidates.len() - 1 } else { 0 };
+        self.set_value(format!("{}{}", prefix, candidates[i]));
+        self.completion = Some(Completion {
+            prefix,
+            candidates,
+            i,
+        });
+    }
+}
+
+// every command, in alphabetical order
+pub fn names() -> Vec<String> {
+    let mut names: Vec<_> = Binding::VARIANTS
+        .iter()
+        .filter(|name| Binding::from_str(name).ok().is_some_and(|b| is_command(&b)))
+        .map(|name| name.replace('_', "-"))
+        .chain(ARG_COMMANDS.iter().map(|name| name.to_string()))
+        .collect();
+    names.sort_unstable();
+
+    names
+}
+
+// bindings that make no sense as commands
+fn is_command(binding: &Binding) -> bool {
+    !matches!(
+        binding,
+        Binding::Other
+            | Binding::Command(_)
+            | Binding::Macro(_)
+            | Binding::CommandLine
+            | Binding::SeekTo
+            | Binding::ScrollPercent
+    )
+}
+
+// `+x` and `-x` are relative, `x` is absolute
+fn parse_amount(s: &str, parse: impl Fn(&str) -> Option<u64>) -> Result<Amount> {
+    let invalid = || anyhow!("invalid value `{}`", s);
+    let relative = |s: &str| parse(s).and_then(|x| i64::try_from(x).ok());
+    match s.as_bytes().first() {
+        Some(b'+') => relative(&s[1..]).map(Amount::By).ok_or_else(invalid),
+        Some(b'-') => relative(&s[1..])
+            .and_then(|x| x.checked_neg())
+            .map(Amount::By)
+            .ok_or_else(invalid),
+        _ => parse(s).map(Amount::To).ok_or_else(invalid),
+    }
+}
+
+// seconds, or [hours:]minutes:seconds (where the minutes and seconds are below 60)
+fn parse_time(s: &str) -> Option<u64> {
+    let parts = s
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if parts.len() > 3 || parts[1..].iter().any(|&part| part >= 60) {
+        return None;
+    }
+
+    parts.into_iter().try_fold(0u64, |seconds, part| {
+        seconds.checked_mul(60)?.checked_add(part)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Command, String> {
+        Command::from_str(s).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn bindings_are_commands() {
+        assert!(matches!(parse("next"), Ok(Command::Binding(Binding::Next))));
+        assert!(matches!(
+            parse("scroll-top"),
+            Ok(Command::Binding(Binding::ScrollTop))
+        ));
+        assert!(matches!(
+            parse(" scroll_top "),
+            Ok(Command::Binding(Binding::ScrollTop))
+        ));
+        assert!(matches!(
+            parse("mode random"),
+            Ok(Command::Binding(Binding::ModeRandom))
+        ));
+    }
+
+    #[test]
+    fn invalid_commands() {
+        assert_eq!(parse("").unwrap_err(), "empty command");
+        assert_eq!(parse("nope").unwrap_err(), "unknown command `nope`");
+        assert_eq!(parse("other").unwrap_err(), "unknown command `other`");
+        assert_eq!(parse("volume").unwrap_err(), "`volume` needs an argument");
+        assert_eq!(
+            parse("next 1").unwrap_err(),
+            "`next` doesn't take any arguments"
+        );
+        assert_eq!(
+            parse("mode loud").unwrap_err(),
+            "invalid mode `loud` (expected one of gapless, random, sequential, single)"
+        );
+    }
+
+    #[test]
+    fn amounts() {
+        assert!(matches!(
+            parse("volume 40"),
+            Ok(Command::Volume(Amount::To(40)))
+        ));
+        assert!(matches!(
+            parse("volume +10"),
+            Ok(Command::Volume(Amount::By(10)))
+        ));
+        assert!(matches!(
+            parse("speed -5"),
+            Ok(Command::Speed(Amount::By(-5)))
+        ));
+        assert!(
+            matches!(parse("volume night"), Ok(Command::VolumePreset(name)) if name == "night")
+        );
+        assert_eq!(parse("volume +x").unwrap_err(), "invalid value `+x`");
+        // these used to overflow
+        assert_eq!(
+            parse("seek -9223372036854775808").unwrap_err(),
+            "invalid value `-9223372036854775808`"
+        );
+        assert_eq!(
+            parse("speed +18446744073709551615").unwrap_err(),
+            "invalid value `+18446744073709551615`"
+        );
+        assert!(matches!(
+            parse("seek -9223372036854775807"),
+            Ok(Command::Seek(Amount::By(-9223372036854775807)))
+        ));
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time("90"), Some(90));
+        assert_eq!(parse_time("1:23"), Some(83));
+        assert_eq!(parse_time("1:23:45"), Some(5025));
+        assert_eq!(parse_time("90:00"), Some(5400));
+        assert_eq!(parse_time("1:99"), None);
+        assert_eq!(parse_time("1:60:00"), None);
+        assert_eq!(parse_time("1:2:3:4"), None);
+        assert_eq!(parse_time("1:"), None);
+        assert_eq!(parse_time("99999999999999999:00:00"), None);
+        assert!(matches!(parse("seek 30%"), Ok(Command::SeekPercent(30))));
+        assert_eq!(parse("seek 101%").unwrap_err(), "invalid percentage `101%`");
+    }
+}
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use ratatui::widgets::TableState;
+use std::{collections::HashMap, sync::Arc};
 
 pub trait Scroll {
     fn scroll(&mut self, delta: i32);
@@ -6,6 +7,34 @@ pub trait Scroll {
     fn scroll_to_bottom(&mut self);
 }
 
+// moves the selection of a list with `n_rows` rows by `delta`, wrapping around at both ends
+pub fn scroll_list(state: &mut TableState, n_rows: usize, delta: i32) {
+    if n_rows == 0 {
+        return;
+    }
+    match state.selected() {
+        Some(r) => {
+            // the list might have shrunk since the selection was made
+            let r = r.min(n_rows - 1) as i64;
+            // a count can make `delta` larger than the whole list
+            let r = (r + delta as i64).rem_euclid(n_rows as i64);
+            state.select(Some(r as usize));
+        }
+        None => state.select_first(),
+    };
+}
+
+pub fn format_time(secs: u64) -> String {
+    let h = secs / 3600;
+    let m = (secs - h * 3600) / 60;
+    let s = secs - h * 3600 - m * 60;
+    if h == 0 {
+        format!("{:02}:{:02}", m, s)
+    } else {
+        format!("{:02}:{:02}:{:02}", h, m, s)
+    }
+}
+
 // for use in screens where the view is split into two parts,
 #[derive(Debug, Default)]
 pub enum FocusedPart {
@@ -15,9 +44,10 @@ pub enum FocusedPart {
 }
 
 // for grouping songs (represents an album or a playlist)
+// the metadata is shared with the search items, so that they don't have to copy it
 #[derive(Clone, Debug, Default)]
 pub struct SongGroup {
-    pub metadata: Vec<HashMap<String, String>>,
+    pub metadata: Vec<Arc<HashMap<String, String>>>,
     pub paths: Vec<String>,
 }
 
@@ -25,7 +55,7 @@ impl SongGroup {
     fn pair_values(
         keys: &[String],
         values: &[Vec<Option<String>>],
-    ) -> Vec<HashMap<String, String>> {
+    ) -> Vec<Arc<HashMap<String, String>>> {
         let mut res = Vec::new();
         for values_inner in values.iter() {
             let mut map = HashMap::new();
@@ -34,7 +64,7 @@ impl SongGroup {
                     map.insert(key.clone(), value.clone());
                 }
             }
-            res.push(map);
+            res.push(Arc::new(map));
         }
 
         res
@@ -52,18 +82,6 @@ impl SongGroup {
         }
     }
 
-    pub fn new_ordered(&self, order: &[usize]) -> Self {
-        let mut ordered = Self::default();
-        for m in order.iter().filter_map(|&i| self.metadata.get(i)) {
-            ordered.metadata.push(m.clone());
-        }
-        for p in order.iter().filter_map(|&i| self.paths.get(i)) {
-            ordered.paths.push(p.clone());
-        }
-
-        ordered
-    }
-
     pub fn add_songs(
         &mut self,
         metadata_keys: &[String],
@@ -83,3 +101,37 @@ impl SongGroup {
         self.metadata.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrolled(selected: Option<usize>, n_rows: usize, delta: i32) -> Option<usize> {
+        let mut state = TableState::default().with_selected(selected);
+        scroll_list(&mut state, n_rows, delta);
+
+        state.selected()
+    }
+
+    #[test]
+    fn scrolling_wraps_around() {
+        assert_eq!(scrolled(Some(0), 10, 1), Some(1));
+        assert_eq!(scrolled(Some(9), 10, 1), Some(0));
+        assert_eq!(scrolled(Some(0), 10, -1), Some(9));
+        assert_eq!(scrolled(Some(3), 10, -5), Some(8));
+        assert_eq!(scrolled(None, 10, 5), Some(0));
+        assert_eq!(scrolled(Some(2), 0, 1), Some(2));
+        // the selection is past the end after the list shrank
+        assert_eq!(scrolled(Some(15), 10, -1), Some(8));
+        assert_eq!(scrolled(Some(15), 10, 1), Some(0));
+    }
+
+    #[test]
+    fn scrolling_more_than_the_list() {
+        assert_eq!(scrolled(Some(0), 10, 30), Some(0));
+        assert_eq!(scrolled(Some(5), 10, 27), Some(2));
+        assert_eq!(scrolled(Some(5), 10, -20), Some(5));
+        assert_eq!(scrolled(Some(0), 10, -9999), Some(1));
+        assert_eq!(scrolled(Some(0), 3, i32::MIN), Some(1));
+    }
+}
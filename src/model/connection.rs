@@ -1,6 +1,7 @@
 use anyhow::{Result, bail};
 use serde_json::{Map, Value as JsonValue, json};
 use std::{
+    cell::Cell,
     collections::HashMap,
     io::{BufReader, prelude::*},
     net::TcpStream,
@@ -11,13 +12,14 @@ use std::{
 use crate::{
     constants,
     event_handler::Event,
-    model::{common::SongGroup, musing::MusingStateDelta},
+    model::{common::SongGroup, library::LibraryView, musing::MusingStateDelta},
 };
 
 #[derive(Debug)]
 pub enum MusingRequest {
     Metadata(Vec<String>, Option<Vec<String>>),
-    GroupedSongs(Vec<String>, Vec<String>),
+    GroupedSongs(LibraryView, Vec<String>),
+    SongInfo(String),
     StateDelta,
     Seek(i64),
     Speed(i16),
@@ -27,15 +29,19 @@ pub enum MusingRequest {
     Remove(u64),
     Update,
     Other(String),
+    // a state delta that arrives only after every earlier request is done
+    Sync,
 }
 
 #[derive(Debug)]
 pub enum MusingResponse {
     Error(String),
     Metadata(Vec<HashMap<String, String>>),
-    GroupedSongs(HashMap<Vec<String>, SongGroup>),
+    GroupedSongs(String, HashMap<Vec<String>, SongGroup>),
+    SongInfo(String, HashMap<String, String>),
     StateDelta(MusingStateDelta),
     Update(String),
+    Synced(Option<MusingStateDelta>),
 }
 
 #[allow(dead_code)]
@@ -43,6 +49,7 @@ pub enum MusingResponse {
 pub struct Connection {
     version: String,
     tx: std_chan::Sender<MusingRequest>,
+    n_sent: Cell<usize>,
 }
 
 impl Connection {
@@ -62,14 +69,23 @@ impl Connection {
         Ok(Self {
             version,
             tx: tx_request,
+            n_sent: Cell::new(0),
         })
     }
 
     pub fn send(&self, request: MusingRequest) {
+        self.n_sent.set(self.n_sent.get() + 1);
         let _ = self.tx.send(request);
     }
+
+    // how many requests have been sent so far
+    pub fn n_sent(&self) -> usize {
+        self.n_sent.get()
+    }
 }
 
+// the errors of sending responses are ignored, so their size doesn't matter
+#[allow(clippy::result_large_err)]
 fn run(
     mut stream: BufReader<TcpStream>,
     tx: std_chan::Sender<Event>,
@@ -83,9 +99,23 @@ fn run(
                 Ok(metadata) => tx.send(Resp(MusingResponse::Metadata(metadata))),
                 Err(e) => tx.send(Resp(MusingResponse::Error(e.to_string()))),
             },
-            MusingRequest::GroupedSongs(group_by, children_tags) => {
-                match grouped_songs(&mut stream, group_by, children_tags) {
-                    Ok(grouped) => tx.send(Resp(MusingResponse::GroupedSongs(grouped))),
+            MusingRequest::GroupedSongs(view, children_tags) => {
+                let LibraryView {
+                    name,
+                    group_by,
+                    sort_by,
+                } = view;
+                match grouped_songs(&mut stream, group_by, children_tags, sort_by) {
+                    Ok(grouped) => tx.send(Resp(MusingResponse::GroupedSongs(name, grouped))),
+                    Err(e) => tx.send(Resp(MusingResponse::Error(e.to_string()))),
+                }
+            }
+            MusingRequest::SongInfo(path) => {
+                match metadata(&mut stream, vec![path.clone()], None) {
+                    Ok(mut metadata) => tx.send(Resp(MusingResponse::SongInfo(
+                        path,
+                        metadata.pop().unwrap_or_default(),
+                    ))),
                     Err(e) => tx.send(Resp(MusingResponse::Error(e.to_string()))),
                 }
             }
@@ -132,6 +162,14 @@ fn run(
                 res.map(|_| ())
                     .or_else(|e| tx.send(Resp(MusingResponse::Error(e.to_string()))))
             }
+            // whoever is waiting gets a response even if it failed
+            MusingRequest::Sync => match state_delta(&mut stream) {
+                Ok(delta) => tx.send(Resp(MusingResponse::Synced(Some(delta)))),
+                Err(e) => {
+                    let _ = tx.send(Resp(MusingResponse::Error(e.to_string())));
+                    tx.send(Resp(MusingResponse::Synced(None)))
+                }
+            },
         };
     }
 }
@@ -204,15 +242,13 @@ pub fn grouped_songs(
     stream: &mut BufReader<TcpStream>,
     group_by: Vec<String>,
     children_tags: Vec<String>,
+    sort_by: Vec<String>,
 ) -> Result<HashMap<Vec<String>, SongGroup>> {
     let mut request = Map::new();
     request.insert("kind".into(), "select".into());
     request.insert("tags".into(), children_tags.clone().into());
     request.insert("group_by".into(), group_by.clone().into());
-    let comparators: Vec<_> = children_tags
-        .iter()
-        .map(|tag| json!({"tag": tag}))
-        .collect();
+    let comparators: Vec<_> = sort_by.iter().map(|tag| json!({"tag": tag})).collect();
     request.insert("comparators".into(), comparators.into());
     write_msg(stream, JsonValue::from(request))?;
     let mut res = read_msg(stream)?;
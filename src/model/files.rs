@@ -0,0 +1,433 @@
+use ratatui::widgets::TableState;
+use std::collections::BTreeMap;
+
+use crate::model::{
+    common::{Scroll, scroll_list},
+    search::{Search, SearchItem},
+};
+
+#[derive(Debug, Default)]
+pub struct Directory {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub subdirs: Vec<usize>, // indices into `FilesState::dirs`, sorted by name
+    pub files: Vec<(String, String)>, // (file name, full path), sorted by name
+}
+
+#[derive(Debug)]
+pub enum Entry<'a> {
+    Directory(&'a Directory),
+    File(&'a str),
+}
+
+#[derive(Debug)]
+pub struct FilesState {
+    pub state: TableState,
+    pub dirs: Vec<Directory>, // the first one is the root
+    pub current: usize,       // the directory being browsed
+    pub search: Search,
+    root_path: String, // the part of the paths common to all songs
+}
+
+impl Default for FilesState {
+    fn default() -> Self {
+        Self {
+            state: TableState::default(),
+            dirs: vec![Directory::default()],
+            current: 0,
+            search: Search::default(),
+            root_path: String::new(),
+        }
+    }
+}
+
+impl Directory {
+    pub fn len(&self) -> usize {
+        self.subdirs.len() + self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Scroll for FilesState {
+    fn scroll(&mut self, delta: i32) {
+        let n_rows = self.search.n_rows(self.dirs[self.current].len());
+        scroll_list(&mut self.state, n_rows, delta);
+    }
+
+    fn scroll_to_top(&mut self) {
+        if self.search.n_rows(self.dirs[self.current].len()) == 0 {
+            return;
+        }
+        self.state.select_first();
+    }
+
+    fn scroll_to_bottom(&mut self) {
+        let n_rows = self.search.n_rows(self.dirs[self.current].len());
+        if n_rows == 0 {
+            return;
+        }
+        self.state.select(Some(n_rows.saturating_sub(1)));
+    }
+}
+
+impl FilesState {
+    // rebuilds the directory tree, trying to stay in the directory that was being browsed
+    pub fn update<'a>(&mut self, paths: impl Iterator<Item = &'a String>) {
+        let current_path = self.path_of(self.current);
+        let mut paths: Vec<_> = paths.collect();
+        paths.sort_unstable();
+        self.root_path = common_dir(&paths);
+
+        // directory path (relative to the root) -> files in that directory
+        let mut tree: BTreeMap<Vec<&str>, Vec<(&str, &str)>> = BTreeMap::new();
+        tree.insert(Vec::new(), Vec::new());
+        for path in paths.iter() {
+            let relative = path[self.root_path.len()..].trim_start_matches('/');
+            let mut parts: Vec<_> = relative.split('/').collect();
+            let file_name = parts.pop().unwrap_or_default();
+            for depth in 1..parts.len() {
+                tree.entry(parts[..depth].to_vec()).or_default();
+            }
+            tree.entry(parts)
+                .or_default()
+                .push((file_name, path.as_str()));
+        }
+
+        self.dirs.clear();
+        let mut indices = BTreeMap::new();
+        // the BTreeMap's order guarantees that parents come before their children
+        for (dir, _) in tree.iter() {
+            let i = self.dirs.len();
+            let parent = dir.split_last().map(|(_, init)| indices[init]);
+            self.dirs.push(Directory {
+                name: dir.last().map(|s| s.to_string()).unwrap_or_default(),
+                parent,
+                ..Default::default()
+            });
+            if let Some(parent) = parent {
+                self.dirs[parent].subdirs.push(i);
+            }
+            indices.insert(dir.clone(), i);
+        }
+        for (dir, files) in tree.iter() {
+            let i = indices[dir];
+            self.dirs[i].files = files
+                .iter()
+                .map(|(name, path)| (name.to_string(), path.to_string()))
+                .collect();
+        }
+
+        self.search.off();
+        self.current = indices
+            .keys()
+            .find(|dir| dir.join("/") == current_path)
+            .map(|dir| indices[dir])
+            .unwrap_or_default();
+        let n_rows = self.dirs[self.current].len();
+        self.state.select(match n_rows {
+            0 => None,
+            _ => Some(self.state.selected().unwrap_or_default().min(n_rows - 1)),
+        });
+    }
+
+    pub fn search_on(&mut self) {
+        self.scroll_to_top();
+        self.search.on(self.entries_to_items());
+    }
+
+    pub fn unordered_selected(&self) -> Option<usize> {
+        self.state.selected().and_then(|i| self.search.real_i(i))
+    }
+
+    pub fn entries_to_items(&self) -> Vec<SearchItem> {
+        let dir = &self.dirs[self.current];
+        dir.subdirs
+            .iter()
+            .map(|&i| self.dirs[i].name.as_str())
+            .chain(dir.files.iter().map(|(name, _)| name.as_str()))
+            .map(|name| SearchItem {
+                repr: unidecode::unidecode(name),
+                tags: Vec::new(),
+            })
+            .collect()
+    }
+
+    pub fn entry(&self, i: usize) -> Option<Entry<'_>> {
+        let dir = &self.dirs[self.current];
+        if i < dir.subdirs.len() {
+            Some(Entry::Directory(&self.dirs[dir.subdirs[i]]))
+        } else {
+            dir.files
+                .get(i - dir.subdirs.len())
+                .map(|(name, _)| Entry::File(name))
+        }
+    }
+
+    // all songs in the selected directory (recursively) or the selected song
+    pub fn selected_songs(&self) -> Vec<String> {
+        let dir = &self.dirs[self.current];
+        match self.unordered_selected() {
+            Some(i) if i < dir.subdirs.len() => {
+                let mut songs = Vec::new();
+                self.collect_songs(dir.subdirs[i], &mut songs);
+
+                songs
+            }
+            Some(i) => dir
+                .files
+                .get(i - dir.subdirs.len())
+                .map(|(_, path)| vec![path.clone()])
+                .unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn selected_file(&self) -> Option<&str> {
+        let dir = &self.dirs[self.current];
+        self.unordered_selected()
+            .and_then(|i| i.checked_sub(dir.subdirs.len()))
+            .and_then(|i| dir.files.get(i))
+            .map(|(_, path)| path.as_str())
+    }
+
+    // descend into the selected directory
+    pub fn enter(&mut self) {
+        let dir = &self.dirs[self.current];
+        if let Some(i) = self.unordered_selected()
+            && i < dir.subdirs.len()
+        {
+            self.current = dir.subdirs[i];
+            self.search.off();
+            self.state.select(if self.dirs[self.current].is_empty() {
+                None
+            } else {
+                Some(0)
+            });
+        }
+    }
+
+    // go back to the parent directory
+    pub fn leave(&mut self) {
+        if let Some(parent) = self.dirs[self.current].parent {
+            let i = self.dirs[parent]
+                .subdirs
+                .iter()
+                .position(|&i| i == self.current);
+            self.current = parent;
+            self.search.off();
+            self.state.select(i);
+        }
+    }
+
+    pub fn current_path(&self) -> String {
+        let relative = self.path_of(self.current);
+        match (self.root_path.is_empty(), relative.is_empty()) {
+            (true, _) => format!("/{}", relative),
+            (false, true) => self.root_path.clone(),
+            (false, false) => format!("{}/{}", self.root_path, relative),
+        }
+    }
+
+    // all songs in the directory at `path` (recursively) or the song at `path`,
+    // which is relative to the root, like the paths shown in the files screen
+    pub fn songs_at(&self, path: &str) -> Vec<String> {
+        let path = path.strip_prefix(self.root_path.as_str()).unwrap_or(path);
+        let path = path.trim_matches('/');
+        let (dir_path, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let Some(i) = self.dir_at(dir_path) else {
+            return Vec::new();
+        };
+        let dir = &self.dirs[i];
+        let mut songs = Vec::new();
+        if name.is_empty() {
+            self.collect_songs(i, &mut songs);
+        } else if let Some(&subdir) = dir.subdirs.iter().find(|&&j| self.dirs[j].name == name) {
+            self.collect_songs(subdir, &mut songs);
+        } else if let Some((_, path)) = dir.files.iter().find(|(file_name, _)| file_name == name) {
+            songs.push(path.clone());
+        }
+
+        songs
+    }
+
+    // the entries in the directory that `path` leads to (directories end with a '/')
+    pub fn completions(&self, path: &str) -> Vec<String> {
+        let (dir_path, prefix) = match path.rsplit_once('/') {
+            Some((dir_path, _)) => (dir_path, format!("{}/", dir_path)),
+            None => ("", String::new()),
+        };
+        let Some(i) = self.dir_at(dir_path) else {
+            return Vec::new();
+        };
+        let dir = &self.dirs[i];
+        dir.subdirs
+            .iter()
+            .map(|&j| format!("{}{}/", prefix, self.dirs[j].name))
+            .chain(
+                dir.files
+                    .iter()
+                    .map(|(name, _)| format!("{}{}", prefix, name)),
+            )
+            .collect()
+    }
+
+    fn dir_at(&self, path: &str) -> Option<usize> {
+        path.split('/')
+            .filter(|part| !part.is_empty())
+            .try_fold(0, |i, part| {
+                self.dirs[i]
+                    .subdirs
+                    .iter()
+                    .copied()
+                    .find(|&j| self.dirs[j].name == part)
+            })
+    }
+
+    fn path_of(&self, mut i: usize) -> String {
+        let mut parts = Vec::new();
+        while let Some(dir) = self.dirs.get(i)
+            && let Some(parent) = dir.parent
+        {
+            parts.push(dir.name.as_str());
+            i = parent;
+        }
+        parts.reverse();
+
+        parts.join("/")
+    }
+
+    fn collect_songs(&self, i: usize, songs: &mut Vec<String>) {
+        let dir = &self.dirs[i];
+        for &subdir in dir.subdirs.iter() {
+            self.collect_songs(subdir, songs);
+        }
+        songs.extend(dir.files.iter().map(|(_, path)| path.clone()));
+    }
+}
+
+// the longest directory that contains all the paths
+fn common_dir(paths: &[&String]) -> String {
+    let (Some(first), Some(last)) = (paths.first(), paths.last()) else {
+        return String::new();
+    };
+    // the paths are sorted, so it's enough to compare the first and the last one
+    let common: Vec<_> = first
+        .split('/')
+        .zip(last.split('/'))
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .map(|(part, _)| part)
+        .collect();
+    let n_dirs = first.split('/').count().saturating_sub(1);
+
+    common[..common.len().min(n_dirs)].join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATHS: [&str; 5] = [
+        "/music/Beatles/Help/01.flac",
+        "/music/Abba/Gold/02.flac",
+        "/music/Abba/Gold/01.flac",
+        "/music/Abba/single.mp3",
+        "/music/loose.mp3",
+    ];
+
+    fn files_state() -> FilesState {
+        let paths: Vec<_> = PATHS.iter().map(|path| path.to_string()).collect();
+        let mut state = FilesState::default();
+        state.update(paths.iter());
+
+        state
+    }
+
+    fn names(state: &FilesState, i: usize) -> (Vec<&str>, Vec<&str>) {
+        let dir = &state.dirs[i];
+        (
+            dir.subdirs
+                .iter()
+                .map(|&j| state.dirs[j].name.as_str())
+                .collect(),
+            dir.files.iter().map(|(name, _)| name.as_str()).collect(),
+        )
+    }
+
+    #[test]
+    fn tree() {
+        let state = files_state();
+        assert_eq!(state.current_path(), "/music");
+        assert_eq!(
+            names(&state, 0),
+            (vec!["Abba", "Beatles"], vec!["loose.mp3"])
+        );
+        let abba = state.dir_at("Abba").unwrap();
+        assert_eq!(names(&state, abba), (vec!["Gold"], vec!["single.mp3"]));
+        let gold = state.dir_at("/Abba/Gold/").unwrap();
+        assert_eq!(state.dirs[gold].parent, Some(abba));
+        assert_eq!(names(&state, gold), (vec![], vec!["01.flac", "02.flac"]));
+        assert_eq!(state.dir_at("Abba/Help"), None);
+        assert_eq!(common_dir(&[]), "");
+    }
+
+    #[test]
+    fn browsing() {
+        let mut state = files_state();
+        state.state.select(Some(0));
+        state.enter();
+        assert_eq!(state.current_path(), "/music/Abba");
+        // the tree gets rebuilt whenever the library arrives, which shouldn't move the user
+        let paths: Vec<_> = PATHS.iter().map(|path| path.to_string()).collect();
+        state.update(paths.iter());
+        assert_eq!(state.current_path(), "/music/Abba");
+        assert_eq!(
+            state.selected_songs(),
+            ["/music/Abba/Gold/01.flac", "/music/Abba/Gold/02.flac"]
+        );
+        state.leave();
+        assert_eq!(state.current_path(), "/music");
+    }
+
+    #[test]
+    fn songs_at() {
+        let state = files_state();
+        assert_eq!(
+            state.songs_at("Abba"),
+            [
+                "/music/Abba/Gold/01.flac",
+                "/music/Abba/Gold/02.flac",
+                "/music/Abba/single.mp3"
+            ]
+        );
+        assert_eq!(
+            state.songs_at("/music/Abba/Gold/02.flac"),
+            ["/music/Abba/Gold/02.flac"]
+        );
+        assert_eq!(
+            state.songs_at("Beatles/Help/"),
+            ["/music/Beatles/Help/01.flac"]
+        );
+        assert_eq!(state.songs_at("/").len(), PATHS.len());
+        assert!(state.songs_at("Abba/Silver").is_empty());
+        assert!(state.songs_at("Queen/x.mp3").is_empty());
+    }
+
+    #[test]
+    fn completions() {
+        let state = files_state();
+        assert_eq!(state.completions(""), ["Abba/", "Beatles/", "loose.mp3"]);
+        assert_eq!(state.completions("Ab"), ["Abba/", "Beatles/", "loose.mp3"]);
+        assert_eq!(
+            state.completions("Abba/G"),
+            ["Abba/Gold/", "Abba/single.mp3"]
+        );
+        assert_eq!(
+            state.completions("Abba/Gold/"),
+            ["Abba/Gold/01.flac", "Abba/Gold/02.flac"]
+        );
+        assert!(state.completions("Queen/").is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use ratatui::widgets::TableState;
+
+use crate::model::{
+    common::{Scroll, SongGroup, scroll_list},
+    library::LibraryChildState,
+    search::{Search, SearchItem},
+};
+
+// every song in the library, searchable at once
+#[derive(Debug, Default)]
+pub struct GlobalSearchState {
+    pub state: TableState,
+    pub songs: SongGroup,
+    pub search: Search,
+}
+
+impl Scroll for GlobalSearchState {
+    fn scroll(&mut self, delta: i32) {
+        let n_rows = self.search.n_rows(self.songs.len());
+        scroll_list(&mut self.state, n_rows, delta);
+    }
+
+    fn scroll_to_top(&mut self) {
+        if self.search.n_rows(self.songs.len()) == 0 {
+            return;
+        }
+        self.state.select_first();
+    }
+
+    fn scroll_to_bottom(&mut self) {
+        let n_rows = self.search.n_rows(self.songs.len());
+        if n_rows == 0 {
+            return;
+        }
+        self.state.select(Some(n_rows - 1));
+    }
+}
+
+impl GlobalSearchState {
+    pub fn update(&mut self, children: &[LibraryChildState]) {
+        self.songs = SongGroup::default();
+        for child in children.iter() {
+            self.songs.metadata.extend_from_slice(&child.group.metadata);
+            self.songs.paths.extend_from_slice(&child.group.paths);
+        }
+        self.search.list_update(|| self.songs_to_items());
+        self.state.select(if self.songs.is_empty() {
+            None
+        } else {
+            let n_rows = self.songs.len();
+            Some(self.state.selected().unwrap_or_default().min(n_rows - 1))
+        });
+    }
+
+    pub fn search_on(&mut self) {
+        self.scroll_to_top();
+        self.search.on(self.songs_to_items());
+    }
+
+    pub fn unordered_selected(&self) -> Option<usize> {
+        self.state.selected().and_then(|i| self.search.real_i(i))
+    }
+
+    pub fn selected_song(&self) -> Option<&str> {
+        self.unordered_selected()
+            .and_then(|i| self.songs.paths.get(i))
+            .map(|s| s.as_str())
+    }
+
+    pub fn songs_to_items(&self) -> Vec<SearchItem> {
+        self.songs
+            .metadata
+            .iter()
+            .zip(self.songs.paths.iter())
+            .map(|(m, path)| {
+                let mut repr = m.get("tracktitle").unwrap_or(path).to_string();
+                for tag in ["artist", "album"] {
+                    if let Some(value) = m.get(tag) {
+                        repr.push(' ');
+                        repr += value;
+                    }
+                }
+
+                SearchItem {
+                    repr: unidecode::unidecode(&repr),
+                    tags: vec![m.clone()],
+                }
+            })
+            .collect()
+    }
+}
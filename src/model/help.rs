@@ -0,0 +1,89 @@
+use ratatui::widgets::TableState;
+
+use crate::model::{
+    common::{Scroll, scroll_list},
+    keybind::Keymap,
+    search::{Search, SearchItem},
+};
+
+// one binding, as shown in the help
+#[derive(Debug)]
+pub struct HelpRow {
+    pub context: String,
+    pub keys: String,
+    pub action: String,
+    pub description: &'static str,
+}
+
+// every binding of the keymap in use, so that it's never out of date
+#[derive(Debug, Default)]
+pub struct HelpState {
+    pub state: TableState,
+    pub rows: Vec<HelpRow>,
+    pub search: Search,
+}
+
+impl Scroll for HelpState {
+    fn scroll(&mut self, delta: i32) {
+        let n_rows = self.search.n_rows(self.rows.len());
+        scroll_list(&mut self.state, n_rows, delta);
+    }
+
+    fn scroll_to_top(&mut self) {
+        if self.search.n_rows(self.rows.len()) == 0 {
+            return;
+        }
+        self.state.select_first();
+    }
+
+    fn scroll_to_bottom(&mut self) {
+        let n_rows = self.search.n_rows(self.rows.len());
+        if n_rows == 0 {
+            return;
+        }
+        self.state.select(Some(n_rows - 1));
+    }
+}
+
+impl HelpState {
+    pub fn new(keymap: &Keymap) -> Self {
+        let rows: Vec<_> = keymap
+            .bindings()
+            .into_iter()
+            .map(|(context, binding, sequences)| HelpRow {
+                context: context.to_string(),
+                keys: sequences.join(", "),
+                action: binding.to_string(),
+                description: binding.description(),
+            })
+            .collect();
+        let mut state = TableState::default();
+        if !rows.is_empty() {
+            state.select_first();
+        }
+
+        Self {
+            state,
+            rows,
+            search: Search::default(),
+        }
+    }
+
+    pub fn search_on(&mut self) {
+        self.scroll_to_top();
+        self.search.on(self.rows_to_items());
+    }
+
+    pub fn rows_to_items(&self) -> Vec<SearchItem> {
+        self.rows
+            .iter()
+            .map(|row| SearchItem {
+                repr: format!(
+                    "{} {} {} {}",
+                    row.context, row.keys, row.action, row.description
+                ),
+                tags: Vec::new(),
+            })
+            .collect()
+    }
+}
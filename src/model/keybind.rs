@@ -1,11 +1,16 @@
 use anyhow::{Result, anyhow, bail};
-use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use std::{collections::HashMap, str::FromStr};
-use strum_macros::EnumString;
-use toml::Table;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MediaKeyCode, ModifierKeyCode};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+use strum_macros::{EnumString, IntoStaticStr, VariantNames};
+use toml::{Table, Value as TomlValue};
 
-// TODO: context aware bindings
-#[derive(Clone, Copy, Debug, EnumString)]
+use crate::model::command::Command;
+
+#[derive(Clone, Debug, EnumString, IntoStaticStr, PartialEq, VariantNames)]
 #[strum(serialize_all = "snake_case")]
 pub enum Binding {
     Quit,
@@ -18,23 +23,36 @@ pub enum Binding {
     Play,
     SeekForwards,
     SeekBackwards,
+    SeekTo,
+    Restart,
     SpeedUp,
     SpeedDown,
     VolumeUp,
     VolumeDown,
+    Mute,
     ScrollUp,
     ScrollDown,
     ScrollManyUp,
     ScrollManyDown,
     ScrollTop,
     ScrollBottom,
+    ScrollPercent,
     FocusLeft,
     FocusRight,
+    NextView,
+    PreviousView,
     StartSearch,
     EndSearch,
+    CycleSearchMode,
+    ToggleCaseSensitive,
+    NextMatch,
+    PreviousMatch,
     AddToQueue,
     RemoveFromQueue,
     ClearQueue,
+    Info,
+    Copy,
+    Reveal,
     ModeGapless,
     ModeRandom,
     ModeSequential,
@@ -43,7 +61,18 @@ pub enum Binding {
     ScreenCover,
     ScreenQueue,
     ScreenLibrary,
-    // used to pass typed characters to search
+    ScreenFiles,
+    ScreenStats,
+    ScreenSearch,
+    CommandLine,
+    Help,
+    // a line typed into the command line, e.g. `volume 40`
+    #[strum(disabled)]
+    Command(String),
+    // one of the macros from the config, e.g. `@play_album`
+    #[strum(disabled)]
+    Macro(String),
+    // used to pass typed characters to search, and to unbind keys
     Other,
 }
 
@@ -56,6 +85,106 @@ pub enum KeybindNode {
 #[derive(Debug)]
 pub struct Keybind(HashMap<KeyEvent, KeybindNode>);
 
+// where a key is pressed, each screen can have its own bindings on top of the global ones
+#[derive(Clone, Copy, Debug, EnumString, Eq, Hash, IntoStaticStr, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+pub enum KeybindContext {
+    Global,
+    Cover,
+    Queue,
+    Library,
+    Files,
+    Stats,
+    Search,
+}
+
+#[derive(Debug)]
+pub struct Keymap {
+    pub global: Keybind,
+    pub contexts: HashMap<KeybindContext, Keybind>,
+}
+
+// the name used in the config (e.g. `scroll_top`)
+impl Display for Binding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Command(line) => write!(f, ":{}", line),
+            Binding::Macro(name) => write!(f, "@{}", name),
+            other => write!(f, "{}", <&str>::from(other)),
+        }
+    }
+}
+
+impl Binding {
+    // shown in the help
+    pub fn description(&self) -> &'static str {
+        match self {
+            Binding::Quit => "quit amusing",
+            Binding::Next => "play the next song",
+            Binding::Previous => "play the previous song",
+            Binding::Pause => "pause the playback",
+            Binding::Resume => "resume the playback",
+            Binding::Toggle => "pause or resume the playback",
+            Binding::Stop => "stop the playback",
+            Binding::Play => "play the selected song",
+            Binding::SeekForwards => "seek forwards by `seek_step` seconds",
+            Binding::SeekBackwards => "seek backwards by `seek_step` seconds",
+            Binding::SeekTo => "type a timestamp (or a percentage) to seek to",
+            Binding::Restart => "play the current song from the start",
+            Binding::SpeedUp => "speed up by `speed_step` percent",
+            Binding::SpeedDown => "slow down by `speed_step` percent",
+            Binding::VolumeUp => "increase the volume by `volume_step`",
+            Binding::VolumeDown => "decrease the volume by `volume_step`",
+            Binding::Mute => "mute, or restore the volume from before muting",
+            Binding::ScrollUp => "select the previous row",
+            Binding::ScrollDown => "select the next row",
+            Binding::ScrollManyUp => "move the selection 5 rows up",
+            Binding::ScrollManyDown => "move the selection 5 rows down",
+            Binding::ScrollTop => "select the first row (or the n-th after a count)",
+            Binding::ScrollBottom => "select the last row (or the n-th after a count)",
+            Binding::ScrollPercent => "jump to n% of the list (after a count)",
+            Binding::FocusLeft => "focus the left part or go to the parent directory",
+            Binding::FocusRight => "focus the right part or enter the directory",
+            Binding::NextView => "switch to the next library view",
+            Binding::PreviousView => "switch to the previous library view",
+            Binding::StartSearch => "start searching (or resume an idle search)",
+            Binding::EndSearch => "stop typing the pattern, then end the search",
+            Binding::CycleSearchMode => "switch between fuzzy, substring and regex searches",
+            Binding::ToggleCaseSensitive => "toggle whether the search is case-sensitive",
+            Binding::NextMatch => "jump to the next match of an idle search",
+            Binding::PreviousMatch => "jump to the previous match of an idle search",
+            Binding::AddToQueue => "add the selected songs to the queue",
+            Binding::RemoveFromQueue => "remove the selected song from the queue",
+            Binding::ClearQueue => "remove every song from the queue",
+            Binding::Info => "show every tag of the selected song",
+            Binding::Copy => "copy the selected tag's value to the clipboard",
+            Binding::Reveal => "show the selected song in the library",
+            Binding::ModeGapless => "toggle gapless playback",
+            Binding::ModeRandom => "play songs in a random order",
+            Binding::ModeSequential => "play songs one after another",
+            Binding::ModeSingle => "stop after the current song",
+            Binding::MusingUpdate => "rescan the music directory",
+            Binding::ScreenCover => "go to the cover art screen",
+            Binding::ScreenQueue => "go to the queue screen",
+            Binding::ScreenLibrary => "go to the library screen",
+            Binding::ScreenFiles => "go to the files screen",
+            Binding::ScreenStats => "go to the stats screen",
+            Binding::ScreenSearch => "go to the search screen",
+            Binding::CommandLine => "open the command line",
+            Binding::Help => "show this help",
+            Binding::Command(_) => "run a command",
+            Binding::Macro(_) => "run a macro",
+            Binding::Other => "",
+        }
+    }
+}
+
+impl Display for KeybindContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", <&str>::from(self))
+    }
+}
+
 impl Default for Keybind {
     fn default() -> Self {
         use KeyModifiers as Mods;
@@ -73,6 +202,11 @@ impl Default for Keybind {
             &[KeyEvent::new(KeyCode::Char('N'), Mods::NONE)],
             Binding::Next,
         );
+        // N is the previous match in the screens with lists, so there's another one
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('n'), Mods::CONTROL)],
+            Binding::Next,
+        );
         keybind.add_keybind(
             &[KeyEvent::new(KeyCode::Char('P'), Mods::NONE)],
             Binding::Previous,
@@ -102,6 +236,14 @@ impl Default for Keybind {
             &[KeyEvent::new(KeyCode::Char('['), Mods::NONE)],
             Binding::SeekBackwards,
         );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('T'), Mods::NONE)],
+            Binding::SeekTo,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('b'), Mods::NONE)],
+            Binding::Restart,
+        );
         keybind.add_keybind(
             &[KeyEvent::new(KeyCode::Char('>'), Mods::NONE)],
             Binding::SpeedUp,
@@ -118,6 +260,10 @@ impl Default for Keybind {
             &[KeyEvent::new(KeyCode::Char('-'), Mods::NONE)],
             Binding::VolumeDown,
         );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('m'), Mods::NONE)],
+            Binding::Mute,
+        );
         keybind.add_keybind(&[KeyEvent::new(KeyCode::Up, Mods::NONE)], Binding::ScrollUp);
         keybind.add_keybind(
             &[KeyEvent::new(KeyCode::Char('k'), Mods::NONE)],
@@ -166,6 +312,10 @@ impl Default for Keybind {
             &[KeyEvent::new(KeyCode::End, Mods::NONE)],
             Binding::ScrollBottom,
         );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('%'), Mods::NONE)],
+            Binding::ScrollPercent,
+        );
         keybind.add_keybind(
             &[KeyEvent::new(KeyCode::Char('h'), Mods::NONE)],
             Binding::FocusLeft,
@@ -182,6 +332,14 @@ impl Default for Keybind {
             &[KeyEvent::new(KeyCode::Right, Mods::NONE)],
             Binding::FocusRight,
         );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('v'), Mods::NONE)],
+            Binding::NextView,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('V'), Mods::NONE)],
+            Binding::PreviousView,
+        );
         keybind.add_keybind(
             &[KeyEvent::new(KeyCode::Char('/'), Mods::NONE)],
             Binding::StartSearch,
@@ -190,6 +348,18 @@ impl Default for Keybind {
             &[KeyEvent::new(KeyCode::Esc, Mods::NONE)],
             Binding::EndSearch,
         );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('t'), Mods::CONTROL)],
+            Binding::CycleSearchMode,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('s'), Mods::CONTROL)],
+            Binding::ToggleCaseSensitive,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('n'), Mods::NONE)],
+            Binding::NextMatch,
+        );
         keybind.add_keybind(
             &[KeyEvent::new(KeyCode::Char('a'), Mods::NONE)],
             Binding::AddToQueue,
@@ -202,6 +372,18 @@ impl Default for Keybind {
             &[KeyEvent::new(KeyCode::Delete, Mods::NONE)],
             Binding::ClearQueue,
         );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('i'), Mods::NONE)],
+            Binding::Info,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('y'), Mods::NONE)],
+            Binding::Copy,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('R'), Mods::NONE)],
+            Binding::Reveal,
+        );
         keybind.add_keybind(
             &[KeyEvent::new(KeyCode::Char('t'), Mods::NONE)],
             Binding::ModeGapless,
@@ -234,38 +416,317 @@ impl Default for Keybind {
             &[KeyEvent::new(KeyCode::Char('3'), Mods::NONE)],
             Binding::ScreenLibrary,
         );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('4'), Mods::NONE)],
+            Binding::ScreenFiles,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('5'), Mods::NONE)],
+            Binding::ScreenStats,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('6'), Mods::NONE)],
+            Binding::ScreenSearch,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char(':'), Mods::NONE)],
+            Binding::CommandLine,
+        );
+        keybind.add_keybind(
+            &[KeyEvent::new(KeyCode::Char('?'), Mods::NONE)],
+            Binding::Help,
+        );
 
         keybind
     }
 }
 
-impl TryFrom<Table> for Keybind {
+impl Default for Keymap {
+    fn default() -> Self {
+        // there's no list in the cover screen to take a count, so digits seek instead (3 -> 30%)
+        let mut cover = Keybind::empty();
+        for digit in 0..10 {
+            cover.add_keybind(
+                &[KeyEvent::new(
+                    KeyCode::Char(char::from_digit(digit, 10).unwrap()),
+                    KeyModifiers::NONE,
+                )],
+                Binding::Command(format!("seek {}%", digit * 10)),
+            );
+        }
+
+        let mut contexts = HashMap::from([(KeybindContext::Cover, cover)]);
+        // vim-style, N jumps to the previous match of an idle search
+        for context in [
+            KeybindContext::Queue,
+            KeybindContext::Library,
+            KeybindContext::Files,
+            KeybindContext::Search,
+        ] {
+            let mut keybind = Keybind::empty();
+            keybind.add_keybind(
+                &[KeyEvent::new(KeyCode::Char('N'), KeyModifiers::NONE)],
+                Binding::PreviousMatch,
+            );
+            contexts.insert(context, keybind);
+        }
+
+        Self {
+            global: Keybind::default(),
+            contexts,
+        }
+    }
+}
+
+// bindings directly in [keybind] are global, [keybind.<context>] tables are per-screen
+impl TryFrom<Table> for Keymap {
     type Error = anyhow::Error;
 
-    fn try_from(table: Table) -> Result<Self> {
-        let mut keybind = Keybind::default();
+    fn try_from(mut table: Table) -> Result<Self> {
+        // `defaults = false` starts from an empty keymap
+        let mut keymap = match table.remove("defaults") {
+            Some(TomlValue::Boolean(true)) | None => Keymap::default(),
+            Some(TomlValue::Boolean(false)) => Keymap {
+                global: Keybind::empty(),
+                contexts: HashMap::new(),
+            },
+            Some(_) => bail!("`defaults` should be true or false"),
+        };
+        let mut entries: HashMap<_, Vec<_>> = HashMap::new();
         for (key, val) in table {
-            let binding = Binding::from_str(&key)?;
-            if let Some(s) = val.as_str() {
-                let key_events = try_into_key_events(s)
-                    .ok_or(anyhow!("could not parse `{}` as a keybinding", s))?;
-                keybind.add_keybind(&key_events, binding);
-            } else if let Some(a) = val.as_array() {
-                for s in a.iter().filter_map(|s| s.as_str()) {
-                    let key_events = try_into_key_events(s)
-                        .ok_or(anyhow!("could not parse `{}` as a keybinding", s))?;
-                    keybind.add_keybind(&key_events, binding);
+            match (KeybindContext::from_str(&key), val) {
+                (Ok(context), TomlValue::Table(table)) => {
+                    entries.entry(context).or_default().extend(table);
                 }
-            } else {
-                bail!("expected a string or an array");
+                (_, val) => entries
+                    .entry(KeybindContext::Global)
+                    .or_default()
+                    .push((key, val)),
             }
         }
+        // the global bindings come first, contexts can unbind them
+        let global = entries.remove(&KeybindContext::Global).unwrap_or_default();
+        keymap.global.configure(global, None)?;
+        for (context, entries) in entries {
+            keymap
+                .contexts
+                .entry(context)
+                .or_insert_with(Keybind::empty)
+                .configure(entries, Some(&keymap.global))
+                .map_err(|e| anyhow!("{} in [keybind.{}]", e, context))?;
+        }
 
-        Ok(keybind)
+        Ok(keymap)
+    }
+}
+
+impl Keymap {
+    // the context's bindings take precedence over the global ones
+    pub fn translate(&self, context: KeybindContext, events: &[KeyEvent]) -> Option<&KeybindNode> {
+        self.contexts
+            .get(&context)
+            .and_then(|keybind| keybind.translate(events))
+            .or_else(|| self.global.translate(events))
+    }
+
+    // every binding of every context, global ones first, with all the sequences bound to it
+    pub fn bindings(&self) -> Vec<(KeybindContext, Binding, Vec<String>)> {
+        let mut contexts: Vec<_> = self.contexts.iter().collect();
+        contexts.sort_unstable_by_key(|(context, _)| context.to_string());
+        let mut bindings = Vec::new();
+        for (context, keybind) in [(&KeybindContext::Global, &self.global)]
+            .into_iter()
+            .chain(contexts)
+        {
+            let mut sequences = Vec::new();
+            keybind.walk(&mut Vec::new(), &mut sequences);
+            // name -> (binding, sequences), so that the bindings come out sorted
+            let mut grouped = BTreeMap::new();
+            // unbound keys aren't worth listing
+            for (events, binding) in sequences.into_iter().filter(|(_, b)| **b != Binding::Other) {
+                grouped
+                    .entry(binding.to_string())
+                    .or_insert_with(|| (binding.clone(), Vec::new()))
+                    .1
+                    .push(key_sequence_to_string(&events));
+            }
+            bindings.extend(grouped.into_values().map(|(binding, mut sequences)| {
+                // e.g. `k` before `<UP_ARROW>`
+                sequences.sort_unstable_by(|lhs, rhs| (lhs.len(), lhs).cmp(&(rhs.len(), rhs)));
+                (*context, binding, sequences)
+            }));
+        }
+
+        bindings
+    }
+
+    // the keys that can follow `events`, along with what they do
+    pub fn continuations(
+        &self,
+        context: KeybindContext,
+        events: &[KeyEvent],
+    ) -> Vec<(String, String)> {
+        let mut next = HashMap::new();
+        // the context's bindings go last, so that they replace the global ones
+        for keybind in [Some(&self.global), self.contexts.get(&context)]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(KeybindNode::Transition(trans)) = keybind.translate(events) {
+                for (ev, node) in trans.0.iter() {
+                    let description = match node {
+                        // unbound in this context
+                        KeybindNode::Terminal(Binding::Other) => {
+                            next.remove(ev);
+                            continue;
+                        }
+                        KeybindNode::Terminal(binding) => binding.to_string(),
+                        KeybindNode::Transition(trans) => format!("+{} more", trans.n_bindings()),
+                    };
+                    next.insert(*ev, description);
+                }
+            }
+        }
+        let mut next: Vec<_> = next
+            .into_iter()
+            .map(|(ev, description)| (key_event_to_string(&ev), description))
+            .collect();
+        next.sort_unstable();
+
+        next
     }
 }
 
 impl Keybind {
+    fn empty() -> Self {
+        Keybind(HashMap::new())
+    }
+
+    // the bindings of an action given in the config replace its default ones,
+    // an empty array (or "none") unbinds it, also hiding the global bindings if done in a context
+    fn configure(
+        &mut self,
+        entries: Vec<(String, TomlValue)>,
+        global: Option<&Keybind>,
+    ) -> Result<()> {
+        let entries = entries
+            .iter()
+            .map(|(key, val)| parse_entry(key, val))
+            .collect::<Result<Vec<_>>>()?;
+        for (binding, _) in entries.iter() {
+            self.unbind(binding);
+        }
+        for (binding, sequences) in entries.iter() {
+            for events in sequences {
+                self.try_add(events, 0, binding)?;
+            }
+        }
+        if let Some(global) = global {
+            let mut global_sequences = Vec::new();
+            global.walk(&mut Vec::new(), &mut global_sequences);
+            global_sequences.sort_unstable_by_key(|(events, _)| key_sequence_to_string(events));
+            let unbound: Vec<_> = entries
+                .iter()
+                .filter(|(_, sequences)| sequences.is_empty())
+                .map(|(binding, _)| binding)
+                .collect();
+            // the context's bindings are looked up first, so a prefix of a global sequence
+            // (or the other way around) makes the global one unreachable in this context
+            for (binding, events) in entries
+                .iter()
+                .flat_map(|(binding, sequences)| sequences.iter().map(move |s| (binding, s)))
+            {
+                for (global_events, global_binding) in global_sequences.iter() {
+                    let n = events.len().min(global_events.len());
+                    if events == global_events
+                        || events[..n] != global_events[..n]
+                        || unbound.contains(global_binding)
+                    {
+                        continue;
+                    }
+                    bail!(
+                        "`{}` ({}) makes the global `{}` ({}) unreachable",
+                        key_sequence_to_string(events),
+                        binding,
+                        key_sequence_to_string(global_events),
+                        global_binding
+                    );
+                }
+            }
+            for binding in unbound {
+                for (events, _) in global_sequences.iter().filter(|(_, b)| *b == binding) {
+                    // keys already used by this context don't need hiding
+                    let _ = self.try_add(events, 0, &Binding::Other);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // like `add_keybind`, but fails instead of overwriting anything
+    fn try_add(&mut self, events: &[KeyEvent], depth: usize, binding: &Binding) -> Result<()> {
+        let sequence = || key_sequence_to_string(&events[..=depth]);
+        let is_last = depth + 1 == events.len();
+        match self.0.get_mut(&events[depth]) {
+            None if is_last => {
+                self.0
+                    .insert(events[depth], KeybindNode::Terminal(binding.clone()));
+            }
+            None => {
+                let mut trans = Keybind::empty();
+                trans.try_add(events, depth + 1, binding)?;
+                self.0.insert(events[depth], KeybindNode::Transition(trans));
+            }
+            Some(KeybindNode::Terminal(existing)) if is_last => {
+                if existing != binding {
+                    bail!(
+                        "`{}` is bound to both `{}` and `{}`",
+                        sequence(),
+                        existing,
+                        binding
+                    );
+                }
+            }
+            Some(KeybindNode::Terminal(existing)) => bail!(
+                "`{}` ({}) shadows `{}` ({})",
+                sequence(),
+                existing,
+                key_sequence_to_string(events),
+                binding
+            ),
+            Some(KeybindNode::Transition(trans)) if is_last => {
+                let mut longer = Vec::new();
+                trans.walk(&mut events.to_vec(), &mut longer);
+                let (longer_events, longer_binding) = longer
+                    .into_iter()
+                    .min_by_key(|(events, _)| key_sequence_to_string(events))
+                    .unwrap();
+                bail!(
+                    "`{}` ({}) shadows `{}` ({})",
+                    sequence(),
+                    binding,
+                    key_sequence_to_string(&longer_events),
+                    longer_binding
+                );
+            }
+            Some(KeybindNode::Transition(trans)) => trans.try_add(events, depth + 1, binding)?,
+        }
+
+        Ok(())
+    }
+
+    // removes every sequence bound to `binding`
+    fn unbind(&mut self, binding: &Binding) {
+        self.0.retain(|_, node| match node {
+            KeybindNode::Terminal(existing) => existing != binding,
+            KeybindNode::Transition(trans) => {
+                trans.unbind(binding);
+                !trans.0.is_empty()
+            }
+        });
+    }
+
     pub fn add_keybind(&mut self, events: &[KeyEvent], binding: Binding) {
         match events.len() {
             0 => (),
@@ -298,6 +759,29 @@ impl Keybind {
         }
     }
 
+    // collects every sequence in the trie, along with its binding
+    fn walk<'a>(&'a self, prefix: &mut Vec<KeyEvent>, out: &mut Vec<(Vec<KeyEvent>, &'a Binding)>) {
+        for (ev, node) in self.0.iter() {
+            prefix.push(*ev);
+            match node {
+                KeybindNode::Terminal(binding) => out.push((prefix.clone(), binding)),
+                KeybindNode::Transition(trans) => trans.walk(prefix, out),
+            }
+            prefix.pop();
+        }
+    }
+
+    fn n_bindings(&self) -> usize {
+        self.0
+            .values()
+            .map(|node| match node {
+                KeybindNode::Terminal(Binding::Other) => 0,
+                KeybindNode::Terminal(_) => 1,
+                KeybindNode::Transition(trans) => trans.n_bindings(),
+            })
+            .sum()
+    }
+
     // Some(Terminal) => this sequence matched something
     // Some(Transition) => this sequence will potentially match something
     // None => this sequence doesn't match and won't match anything in the future
@@ -313,42 +797,342 @@ impl Keybind {
     }
 }
 
-fn try_into_code(s: impl AsRef<str>) -> Option<KeyCode> {
-    let s = s.as_ref();
-    if s.len() == 1 {
-        s.chars().next().map(KeyCode::Char)
-    } else {
-        match s {
-            "<SPACE>" => Some(KeyCode::Char(' ')),
-            "<UP_ARROW>" => Some(KeyCode::Up),
-            "<DOWN_ARROW>" => Some(KeyCode::Down),
-            "<LEFT_ARROW>" => Some(KeyCode::Left),
-            "<RIGHT_ARROW>" => Some(KeyCode::Right),
-            "<ENTER>" => Some(KeyCode::Enter),
-            "<ESCAPE>" => Some(KeyCode::Esc),
-            "<TAB>" => Some(KeyCode::Tab),
-            "<BACKSPACE>" => Some(KeyCode::Backspace),
-            "<DELETE>" => Some(KeyCode::Delete),
-            "<HOME>" => Some(KeyCode::Home),
-            "<END>" => Some(KeyCode::End),
-            "<PAGE_UP>" => Some(KeyCode::PageUp),
-            "<PAGE_DOWN>" => Some(KeyCode::PageDown),
-            _ => None,
+// special keys, written in angle brackets (e.g. <F5>, C-<ENTER>)
+// a key is printed with the first of its names
+const KEY_NAMES: [(&str, KeyCode); 56] = [
+    ("SPACE", KeyCode::Char(' ')),
+    ("UP_ARROW", KeyCode::Up),
+    ("UP", KeyCode::Up),
+    ("DOWN_ARROW", KeyCode::Down),
+    ("DOWN", KeyCode::Down),
+    ("LEFT_ARROW", KeyCode::Left),
+    ("LEFT", KeyCode::Left),
+    ("RIGHT_ARROW", KeyCode::Right),
+    ("RIGHT", KeyCode::Right),
+    ("ENTER", KeyCode::Enter),
+    ("ESCAPE", KeyCode::Esc),
+    ("ESC", KeyCode::Esc),
+    ("TAB", KeyCode::Tab),
+    ("BACKTAB", KeyCode::BackTab),
+    ("BACKSPACE", KeyCode::Backspace),
+    ("DELETE", KeyCode::Delete),
+    ("INSERT", KeyCode::Insert),
+    ("HOME", KeyCode::Home),
+    ("END", KeyCode::End),
+    ("PAGE_UP", KeyCode::PageUp),
+    ("PAGE_DOWN", KeyCode::PageDown),
+    ("NULL", KeyCode::Null),
+    ("CAPS_LOCK", KeyCode::CapsLock),
+    ("SCROLL_LOCK", KeyCode::ScrollLock),
+    ("NUM_LOCK", KeyCode::NumLock),
+    ("PRINT_SCREEN", KeyCode::PrintScreen),
+    ("PAUSE", KeyCode::Pause),
+    ("MENU", KeyCode::Menu),
+    ("KEYPAD_BEGIN", KeyCode::KeypadBegin),
+    ("MEDIA_PLAY", KeyCode::Media(MediaKeyCode::Play)),
+    ("MEDIA_PAUSE", KeyCode::Media(MediaKeyCode::Pause)),
+    ("MEDIA_PLAY_PAUSE", KeyCode::Media(MediaKeyCode::PlayPause)),
+    ("MEDIA_REVERSE", KeyCode::Media(MediaKeyCode::Reverse)),
+    ("MEDIA_STOP", KeyCode::Media(MediaKeyCode::Stop)),
+    (
+        "MEDIA_FAST_FORWARD",
+        KeyCode::Media(MediaKeyCode::FastForward),
+    ),
+    ("MEDIA_REWIND", KeyCode::Media(MediaKeyCode::Rewind)),
+    ("MEDIA_NEXT", KeyCode::Media(MediaKeyCode::TrackNext)),
+    (
+        "MEDIA_PREVIOUS",
+        KeyCode::Media(MediaKeyCode::TrackPrevious),
+    ),
+    ("MEDIA_RECORD", KeyCode::Media(MediaKeyCode::Record)),
+    ("VOLUME_DOWN", KeyCode::Media(MediaKeyCode::LowerVolume)),
+    ("VOLUME_UP", KeyCode::Media(MediaKeyCode::RaiseVolume)),
+    ("VOLUME_MUTE", KeyCode::Media(MediaKeyCode::MuteVolume)),
+    ("LEFT_SHIFT", KeyCode::Modifier(ModifierKeyCode::LeftShift)),
+    (
+        "LEFT_CONTROL",
+        KeyCode::Modifier(ModifierKeyCode::LeftControl),
+    ),
+    ("LEFT_ALT", KeyCode::Modifier(ModifierKeyCode::LeftAlt)),
+    ("LEFT_SUPER", KeyCode::Modifier(ModifierKeyCode::LeftSuper)),
+    ("LEFT_HYPER", KeyCode::Modifier(ModifierKeyCode::LeftHyper)),
+    ("LEFT_META", KeyCode::Modifier(ModifierKeyCode::LeftMeta)),
+    (
+        "RIGHT_SHIFT",
+        KeyCode::Modifier(ModifierKeyCode::RightShift),
+    ),
+    (
+        "RIGHT_CONTROL",
+        KeyCode::Modifier(ModifierKeyCode::RightControl),
+    ),
+    ("RIGHT_ALT", KeyCode::Modifier(ModifierKeyCode::RightAlt)),
+    (
+        "RIGHT_SUPER",
+        KeyCode::Modifier(ModifierKeyCode::RightSuper),
+    ),
+    (
+        "RIGHT_HYPER",
+        KeyCode::Modifier(ModifierKeyCode::RightHyper),
+    ),
+    ("RIGHT_META", KeyCode::Modifier(ModifierKeyCode::RightMeta)),
+    (
+        "ISO_LEVEL3_SHIFT",
+        KeyCode::Modifier(ModifierKeyCode::IsoLevel3Shift),
+    ),
+    (
+        "ISO_LEVEL5_SHIFT",
+        KeyCode::Modifier(ModifierKeyCode::IsoLevel5Shift),
+    ),
+];
+
+// prefixes of modifiers, in the order in which they're printed
+// (M- is another name for A-, like in Emacs, so meta gets spelled out)
+const MODIFIER_PREFIXES: [(&str, KeyModifiers); 7] = [
+    ("C-", KeyModifiers::CONTROL),
+    ("A-", KeyModifiers::ALT),
+    ("M-", KeyModifiers::ALT),
+    ("S-", KeyModifiers::SHIFT),
+    ("D-", KeyModifiers::SUPER),
+    ("H-", KeyModifiers::HYPER),
+    ("Meta-", KeyModifiers::META),
+];
+
+// `val` is either a string or an array of strings, e.g. quit = ["q", "C-c"],
+// an empty array or "none" means no sequences at all
+fn parse_entry(key: &str, val: &TomlValue) -> Result<(Binding, Vec<Vec<KeyEvent>>)> {
+    // `":volume 40" = "<F9>"` binds a command, `"@play_album" = "M"` binds a macro
+    // (whether that macro exists is checked along with the rest of the config)
+    let binding = match (key.strip_prefix(':'), key.strip_prefix('@')) {
+        (Some(command), _) => {
+            Command::from_str(command)
+                .map_err(|e| anyhow!("invalid command `{}` ({})", command, e))?;
+            Binding::Command(command.to_string())
+        }
+        (_, Some(name)) => Binding::Macro(name.to_string()),
+        _ => Binding::from_str(key)
+            .ok()
+            .filter(|binding| *binding != Binding::Other)
+            .ok_or(anyhow!("invalid keybind action `{}`", key))?,
+    };
+    let sequences = match val {
+        TomlValue::String(s) if s == "none" => vec![],
+        TomlValue::String(s) => vec![s.as_str()],
+        TomlValue::Array(a) => a
+            .iter()
+            .map(|s| s.as_str().ok_or(anyhow!("expected a string in `{}`", key)))
+            .collect::<Result<_>>()?,
+        _ => bail!("expected a string or an array"),
+    };
+    let sequences = sequences
+        .into_iter()
+        .map(|s| {
+            parse_key_sequence(s)
+                .map_err(|e| anyhow!("invalid keybinding `{}` for `{}` ({})", s, key, e))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok((binding, sequences))
+}
+
+fn parse_code(s: &str) -> Result<KeyCode> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    let Some(name) = s.strip_prefix('<').and_then(|s| s.strip_suffix('>')) else {
+        match KEY_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+        {
+            Some((name, _)) => bail!("special keys go in angle brackets (`<{}>`)", name),
+            None => bail!("`{}` is neither a single character nor a <SPECIAL_KEY>", s),
+        }
+    };
+    if let Some(n) = name.strip_prefix('F')
+        && !n.is_empty()
+        && n.chars().all(|c| c.is_ascii_digit())
+    {
+        return match n.parse::<u8>() {
+            Ok(n @ 1..) => Ok(KeyCode::F(n)),
+            _ => bail!("there's no function key `<F{}>`", n),
+        };
+    }
+
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| *key_name == name)
+        .map(|(_, code)| *code)
+        .ok_or(anyhow!("unknown key `<{}>`", name))
+}
+
+// e.g. "C-S-p", "M-x", "<F5>", "C-<ENTER>"
+fn parse_key_event(s: &str) -> Result<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = s;
+    // a lone "C-" is not a prefix of anything
+    while let Some((prefix, modifier)) = MODIFIER_PREFIXES
+        .iter()
+        .find(|(prefix, _)| rest.len() > prefix.len() && rest.starts_with(prefix))
+    {
+        if modifiers.contains(*modifier) {
+            bail!("the modifier `{}` is repeated", prefix);
         }
+        modifiers |= *modifier;
+        rest = &rest[prefix.len()..];
+    }
+    let code = parse_code(rest)?;
+    // terminals report shift+tab as a separate key, still with shift held
+    let (code, modifiers) = match code {
+        KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => (KeyCode::BackTab, modifiers),
+        KeyCode::BackTab => (code, modifiers | KeyModifiers::SHIFT),
+        _ => (code, modifiers),
+    };
+
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+// keys separated by spaces, e.g. "g g" or "C-x C-p"
+pub fn parse_key_sequence(s: &str) -> Result<Vec<KeyEvent>> {
+    // a lone space can't be split on
+    if s == " " {
+        return Ok(vec![KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE)]);
+    }
+    let events = s
+        .split_whitespace()
+        .map(|part| parse_key_event(part).map_err(|e| anyhow!("`{}`: {}", part, e)))
+        .collect::<Result<Vec<_>>>()?;
+    if events.is_empty() {
+        bail!("empty key sequence");
     }
+
+    Ok(events)
 }
 
-fn try_into_key_events(s: impl AsRef<str>) -> Option<Vec<KeyEvent>> {
-    let mut res = Vec::new();
-    for part in s.as_ref().split_whitespace() {
-        if let Some(code) = part.strip_prefix("C-").and_then(try_into_code) {
-            res.push(KeyEvent::new(code, KeyModifiers::CONTROL));
-        } else if let Some(code) = try_into_code(part) {
-            res.push(KeyEvent::new(code, KeyModifiers::NONE));
-        } else {
-            return None;
+// the inverse of `parse_key_event`
+pub fn key_event_to_string(ev: &KeyEvent) -> String {
+    let mut modifiers = ev.modifiers;
+    let code = match ev.code {
+        KeyCode::Char(' ') => "<SPACE>".to_string(),
+        KeyCode::Char(c) => {
+            // an uppercase letter already implies shift
+            if c.is_ascii_alphabetic() && modifiers.contains(KeyModifiers::SHIFT) {
+                modifiers.remove(KeyModifiers::SHIFT);
+                c.to_ascii_uppercase().to_string()
+            } else {
+                c.to_string()
+            }
+        }
+        KeyCode::F(n) => format!("<F{}>", n),
+        KeyCode::BackTab => {
+            modifiers.remove(KeyModifiers::SHIFT);
+            "<BACKTAB>".to_string()
+        }
+        code => KEY_NAMES
+            .iter()
+            .find(|(_, key_code)| *key_code == code)
+            .map_or_else(|| format!("{:?}", code), |(name, _)| format!("<{}>", name)),
+    };
+    let mut res = String::new();
+    for (prefix, modifier) in MODIFIER_PREFIXES.iter() {
+        if modifiers.contains(*modifier) {
+            modifiers.remove(*modifier);
+            res += prefix;
         }
     }
 
-    Some(res)
+    res + &code
+}
+
+pub fn key_sequence_to_string(events: &[KeyEvent]) -> String {
+    events
+        .iter()
+        .map(key_event_to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(s: &str) -> String {
+        parse_key_sequence(s).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn key_sequences_round_trip() {
+        for s in [
+            "q",
+            "Q",
+            "g g",
+            "C-x C-p",
+            "C-S-p",
+            "A-x",
+            "D-H-x",
+            "Meta-x",
+            "C-Meta-<ENTER>",
+            "<SPACE>",
+            "<F12>",
+            "<BACKTAB>",
+            "S-<TAB>",
+            "S-1",
+            "<MEDIA_PLAY_PAUSE>",
+            "-",
+        ] {
+            let events = parse_key_sequence(s).unwrap();
+            let printed = key_sequence_to_string(&events);
+            assert_eq!(parse_key_sequence(&printed).unwrap(), events, "{}", s);
+        }
+    }
+
+    #[test]
+    fn key_sequences_print_canonically() {
+        let print = |s: &str| key_sequence_to_string(&parse_key_sequence(s).unwrap());
+        assert_eq!(print("M-x"), "A-x");
+        assert_eq!(print("S-<TAB>"), "<BACKTAB>");
+        assert_eq!(print(" "), "<SPACE>");
+        assert_eq!(print("Meta-S-a"), "Meta-A");
+        assert_eq!(print("<ESC>"), "<ESCAPE>");
+    }
+
+    #[test]
+    fn modifiers() {
+        let event = |s: &str| parse_key_sequence(s).unwrap()[0];
+        assert_eq!(
+            event("Meta-x"),
+            KeyEvent::new(KeyCode::Char('x'), KeyModifiers::META)
+        );
+        assert_eq!(
+            event("C-M-x"),
+            KeyEvent::new(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_key_sequences() {
+        assert_eq!(parse_err(""), "empty key sequence");
+        assert_eq!(parse_err("C-C-x"), "`C-C-x`: the modifier `C-` is repeated");
+        assert_eq!(parse_err("A-M-x"), "`A-M-x`: the modifier `M-` is repeated");
+        assert_eq!(
+            parse_err("g enter"),
+            "`enter`: special keys go in angle brackets (`<ENTER>`)"
+        );
+        assert_eq!(
+            parse_err("xy"),
+            "`xy`: `xy` is neither a single character nor a <SPECIAL_KEY>"
+        );
+        assert_eq!(
+            parse_err("Meta-"),
+            "`Meta-`: `Meta-` is neither a single character nor a <SPECIAL_KEY>"
+        );
+        assert_eq!(parse_err("<NOPE>"), "`<NOPE>`: unknown key `<NOPE>`");
+        assert_eq!(parse_err("<F0>"), "`<F0>`: there's no function key `<F0>`");
+        assert_eq!(
+            parse_err("<F300>"),
+            "`<F300>`: there's no function key `<F300>`"
+        );
+    }
 }
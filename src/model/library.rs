@@ -1,11 +1,25 @@
+use anyhow::{Result, anyhow, bail};
 use ratatui::widgets::TableState;
-use std::collections::HashMap;
+use std::{collections::HashMap, mem, sync::Arc};
+use toml::{Table, Value as TomlValue};
 
-use crate::model::{
-    common::{FocusedPart, Scroll, SongGroup},
-    search::{Search, SearchState},
+use crate::{
+    constants,
+    model::{
+        collation::Collation,
+        common::{FocusedPart, Scroll, SongGroup, scroll_list},
+        search::{Search, SearchItem},
+    },
 };
 
+// a named way of grouping the library (e.g. by album, by genre, by year)
+#[derive(Clone, Debug)]
+pub struct LibraryView {
+    pub name: String,
+    pub group_by: Vec<String>,
+    pub sort_by: Vec<String>, // tags used to order songs within a group
+}
+
 #[derive(Debug, Default)]
 pub struct LibraryChildState {
     pub state: TableState,
@@ -18,71 +32,99 @@ pub struct LibraryChildState {
 pub struct LibraryState {
     pub state: TableState,
     pub focused_part: FocusedPart,
-    pub group_by_tags: Vec<String>,
+    pub views: Vec<LibraryView>,
+    pub current_view: usize,
+    pub collation: Collation,
     pub children_tags: Vec<String>,
     pub children: Vec<LibraryChildState>, // grouped collections of songs
     pub search: Search,
+    // children (and the selection) of views other than the current one
+    cache: HashMap<String, (TableState, Vec<LibraryChildState>)>,
+}
+
+impl LibraryView {
+    pub fn new(name: impl Into<String>, group_by: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            group_by,
+            sort_by: constants::DEFAULT_SORT_BY
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<Table> for LibraryView {
+    type Error = anyhow::Error;
+
+    fn try_from(table: Table) -> Result<Self> {
+        let mut name = None;
+        let mut view = LibraryView::new("", Vec::new());
+        for (key, val) in table {
+            match (key.as_str(), val) {
+                ("name", TomlValue::String(s)) => name = Some(s),
+                ("group_by", TomlValue::Array(group_by)) => {
+                    view.group_by = group_by
+                        .iter()
+                        .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                        .collect();
+                }
+                ("sort_by", TomlValue::Array(sort_by)) => {
+                    view.sort_by = sort_by
+                        .iter()
+                        .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                        .collect();
+                }
+                (other, _) => bail!("invalid config key `{}`", other),
+            }
+        }
+        view.name = name.ok_or(anyhow!("a library view needs a `name`"))?;
+        if view.group_by.is_empty() {
+            bail!("library view `{}` needs a non-empty `group_by`", view.name);
+        }
+
+        Ok(view)
+    }
 }
 
 impl LibraryChildState {
     pub fn search_on(&mut self) {
         self.state.select_first();
-        self.search.on(self.songs_to_repr());
+        self.search.on(self.songs_to_items());
     }
 
     pub fn unordered_selected(&self) -> Option<usize> {
-        self.state.selected().map(|i| self.search.real_i(i))
+        self.state.selected().and_then(|i| self.search.real_i(i))
     }
 
-    pub fn songs_to_repr(&self) -> Vec<String> {
+    pub fn songs_to_items(&self) -> Vec<SearchItem> {
         self.group
             .metadata
             .iter()
             .zip(self.group.paths.iter())
             .map(|(m, path)| {
                 let repr = m.get("tracktitle").unwrap_or(path);
-                unidecode::unidecode(repr)
+                SearchItem {
+                    repr: unidecode::unidecode(repr),
+                    tags: vec![m.clone()],
+                }
             })
             .collect()
     }
-
-    pub fn ordered_group(&self) -> Vec<(&HashMap<String, String>, &String)> {
-        let search = &self.search;
-        match search.state {
-            SearchState::Off => self
-                .group
-                .metadata
-                .iter()
-                .zip(self.group.paths.iter())
-                .collect(),
-            _ => {
-                let mut ordered = Vec::with_capacity(self.group.metadata.len());
-                // clone not to hold up the guard
-                let order = { search.result.read().unwrap().clone() };
-                for i in order.iter() {
-                    if let Some(m) = self.group.metadata.get(*i)
-                        && let Some(path) = self.group.paths.get(*i)
-                    {
-                        ordered.push((m, path));
-                    }
-                }
-
-                ordered
-            }
-        }
-    }
 }
 
 impl Default for LibraryState {
     fn default() -> Self {
-        Self {
-            state: TableState::default(),
-            focused_part: FocusedPart::default(),
-            group_by_tags: vec!["albumartist".into(), "album".into()],
-            children_tags: vec!["tracknumber".into(), "tracktitle".into()],
-            children: Vec::new(),
-            search: Search::default(),
-        }
+        let group_by = constants::DEFAULT_GROUP_BY
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        Self::new(
+            vec![LibraryView::new(constants::DEFAULT_VIEW_NAME, group_by)],
+            Collation::default(),
+        )
     }
 }
 
@@ -91,36 +133,20 @@ impl Scroll for LibraryState {
         if self.children.is_empty() {
             return;
         }
-        let u_delta = delta.unsigned_abs() as usize;
         let (n_rows, state) = match self.focused_part {
             FocusedPart::Groups => {
                 if let Some(child) = self.selected_child_mut() {
                     child.search.off();
                 }
 
-                (self.children.len(), &mut self.state)
+                (self.search.n_rows(self.children.len()), &mut self.state)
             }
-            FocusedPart::Child(i) => (
-                self.children[i].group.paths.len(),
-                &mut self.children[i].state,
-            ),
-        };
-        match state.selected() {
-            Some(r) => {
-                if delta < 0 {
-                    if r >= u_delta {
-                        state.scroll_up_by(u_delta as u16);
-                    } else {
-                        state.select(Some(n_rows - (u_delta - r)));
-                    }
-                } else if r + u_delta < n_rows {
-                    state.scroll_down_by(u_delta as u16);
-                } else {
-                    state.select(Some(u_delta - (n_rows - r)));
-                }
+            FocusedPart::Child(i) => {
+                let child = &mut self.children[i];
+                (child.search.n_rows(child.group.len()), &mut child.state)
             }
-            None => state.select_first(),
         };
+        scroll_list(state, n_rows, delta);
     }
 
     fn scroll_to_top(&mut self) {
@@ -138,39 +164,104 @@ impl Scroll for LibraryState {
             return;
         }
         match self.focused_part {
-            FocusedPart::Groups => self
-                .state
-                .select(Some(self.children.len().saturating_sub(1))),
+            FocusedPart::Groups => {
+                let n = self.search.n_rows(self.children.len());
+                self.state.select(n.checked_sub(1));
+            }
             FocusedPart::Child(i) => {
-                let n = self.children[i].group.paths.len();
-                self.children[i].state.select(Some(n.saturating_sub(1)));
+                let child = &mut self.children[i];
+                let n = child.search.n_rows(child.group.len());
+                child.state.select(n.checked_sub(1));
             }
         }
     }
 }
 
 impl LibraryState {
-    pub fn new(group_by_tags: Vec<String>) -> Self {
+    pub fn new(views: Vec<LibraryView>, collation: Collation) -> Self {
         Self {
             state: TableState::default(),
             focused_part: FocusedPart::default(),
-            group_by_tags,
-            children_tags: vec!["tracknumber".into(), "tracktitle".into()],
+            views,
+            current_view: 0,
+            collation,
+            children_tags: constants::CHILDREN_TAGS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
             children: Vec::new(),
             search: Search::default(),
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn view(&self) -> &LibraryView {
+        &self.views[self.current_view]
+    }
+
+    // tags to fetch for the songs of the current view
+    pub fn fetched_tags(&self) -> Vec<String> {
+        let mut tags = self.children_tags.clone();
+        for tag in self.collation.extra_tags(&self.view().group_by) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+
+        tags
+    }
+
+    // returns true if the songs of the new view have to be fetched from musing
+    pub fn switch_view(&mut self, i: usize) -> bool {
+        if i == self.current_view || i >= self.views.len() {
+            return false;
+        }
+        if let Some(child) = self.selected_child_mut() {
+            child.search.off();
+        }
+        self.focus_left();
+        self.search.off();
+        let state = mem::take(&mut self.state);
+        let children = mem::take(&mut self.children);
+        self.cache
+            .insert(self.view().name.clone(), (state, children));
+        self.current_view = i;
+
+        match self.cache.remove(&self.views[i].name) {
+            Some((state, children)) => {
+                self.state = state;
+                self.children = children;
+                self.clamp_selection();
+                false
+            }
+            None => true,
         }
     }
 
+    pub fn cycle_view(&mut self, delta: isize) -> bool {
+        let n = self.views.len() as isize;
+        let i = (self.current_view as isize + delta).rem_euclid(n) as usize;
+
+        self.switch_view(i)
+    }
+
+    // to be called when the library changes, since every cached view is then outdated
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
     pub fn search_on(&mut self) {
         self.scroll_to_top();
-        self.search.on(self.children_to_repr());
+        self.search.on(self.children_to_items());
     }
 
     pub fn unordered_selected(&self) -> Option<usize> {
-        self.state.selected().map(|i| self.search.real_i(i))
+        self.state.selected().and_then(|i| self.search.real_i(i))
     }
 
-    pub fn children_to_repr(&self) -> Vec<String> {
+    // a group matches a tag filter if any of its songs does
+    pub fn children_to_items(&self) -> Vec<SearchItem> {
+        let group_by = &self.view().group_by;
         self.children
             .iter()
             .map(|child| {
@@ -179,25 +270,53 @@ impl LibraryState {
                     repr += value;
                     repr.push(' ');
                 }
+                // the values of the grouping tags (which might not have been fetched for the songs)
+                let group_tags = group_by
+                    .iter()
+                    .cloned()
+                    .zip(child.id_comb.iter().cloned())
+                    .collect();
+                let mut tags = child.group.metadata.clone();
+                tags.push(Arc::new(group_tags));
 
-                unidecode::unidecode(&repr)
+                SearchItem {
+                    repr: unidecode::unidecode(&repr),
+                    tags,
+                }
             })
             .collect()
     }
 
-    pub fn update(&mut self, grouped_songs: HashMap<Vec<String>, SongGroup>) {
-        self.children.clear();
-        for (id_comb, group) in grouped_songs {
-            let child = LibraryChildState {
+    pub fn update(&mut self, view_name: String, grouped_songs: HashMap<Vec<String>, SongGroup>) {
+        let mut children: Vec<_> = grouped_songs
+            .into_iter()
+            .map(|(id_comb, group)| LibraryChildState {
                 state: TableState::default(),
                 id_comb,
                 group,
                 search: Search::default(),
-            };
-            self.children.push(child);
+            })
+            .collect();
+        if let Some(view) = self.views.iter().find(|view| view.name == view_name) {
+            children.sort_by_cached_key(|child| {
+                self.collation.sort_key(
+                    &view.group_by,
+                    &child.id_comb,
+                    child.group.metadata.first().map(|m| m.as_ref()),
+                )
+            });
         }
-        self.children
-            .sort_unstable_by(|lhs, rhs| (lhs.id_comb).cmp(&rhs.id_comb));
+        if view_name != self.view().name {
+            self.cache
+                .insert(view_name, (TableState::default(), children));
+            return;
+        }
+        self.children = children;
+        self.clamp_selection();
+        self.focus_left();
+    }
+
+    fn clamp_selection(&mut self) {
         if self.children.is_empty() {
             self.state.select(None);
         } else {
@@ -207,7 +326,6 @@ impl LibraryState {
                     .map_or(0, |i| i.min(self.children.len().saturating_sub(1))),
             ));
         }
-        self.focus_left();
     }
 
     pub fn selected_child(&self) -> Option<&LibraryChildState> {
@@ -233,36 +351,38 @@ impl LibraryState {
     }
 
     pub fn focus_left(&mut self) {
-        if let Some(i) = self.state.selected() {
-            self.state.select(Some(i));
-            let real_i = self.search.real_i(i);
+        if let Some(real_i) = self.unordered_selected() {
             self.children[real_i].state.select(None);
         }
         self.focused_part = FocusedPart::Groups;
     }
 
     pub fn focus_right(&mut self) {
-        if let Some(i) = self.state.selected() {
-            let real_i = self.search.real_i(i);
+        if let Some(real_i) = self.unordered_selected() {
             self.children[real_i].state.select_first();
             self.focused_part = FocusedPart::Child(real_i);
         }
     }
 
-    pub fn ordered_children(&self) -> Vec<&LibraryChildState> {
-        let search = &self.search;
-        match search.state {
-            SearchState::Off => self.children.iter().collect(),
-            _ => {
-                let mut ordered = Vec::with_capacity(self.children.len());
-                // clone not to hold up the guard
-                let order = { search.result.read().unwrap().clone() };
-                for child in order.iter().filter_map(|&i| self.children.get(i)) {
-                    ordered.push(child);
-                }
+    // select the group containing the song and the song itself, false if it's not in this view
+    pub fn reveal(&mut self, path: &str) -> bool {
+        let found = self.children.iter().enumerate().find_map(|(i, child)| {
+            child
+                .group
+                .paths
+                .iter()
+                .position(|p| p == path)
+                .map(|j| (i, j))
+        });
+        let Some((i, j)) = found else {
+            return false;
+        };
+        self.search.off();
+        self.children[i].search.off();
+        self.state.select(Some(i));
+        self.focus_right();
+        self.children[i].state.select(Some(j));
 
-                ordered
-            }
-        }
+        true
     }
 }
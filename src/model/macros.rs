@@ -0,0 +1,105 @@
+use anyhow::{Result, anyhow, bail};
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+};
+use toml::{Table, Value as TomlValue};
+
+use crate::model::command::Command;
+
+// name -> the commands it runs, in order
+#[derive(Debug, Default)]
+pub struct Macros(HashMap<String, Vec<Command>>);
+
+// the macro being run at the moment
+#[derive(Debug, Default)]
+pub struct MacroState {
+    name: String,
+    steps: VecDeque<Command>,
+    waiting: bool, // for musing to finish the requests of the previous step
+}
+
+impl TryFrom<Table> for Macros {
+    type Error = anyhow::Error;
+
+    // every step is either an action (e.g. "clear_queue") or a command (e.g. ":mode random")
+    fn try_from(table: Table) -> Result<Self> {
+        let mut macros = HashMap::new();
+        for (name, val) in table {
+            let TomlValue::Array(steps) = val else {
+                bail!("macro `{}` should be an array", name);
+            };
+            let steps = steps
+                .iter()
+                .map(|step| {
+                    let step = step
+                        .as_str()
+                        .ok_or(anyhow!("the steps of macro `{}` should be strings", name))?;
+                    Command::from_str(step.strip_prefix(':').unwrap_or(step))
+                        .map_err(|e| anyhow!("invalid step `{}` in macro `{}` ({})", step, name, e))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if steps.is_empty() {
+                bail!("macro `{}` has no steps", name);
+            }
+            macros.insert(name, steps);
+        }
+
+        Ok(Self(macros))
+    }
+}
+
+impl Macros {
+    pub fn get(&self, name: &str) -> Option<&[Command]> {
+        self.0.get(name).map(|steps| steps.as_slice())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    // the steps of every macro
+    pub fn commands(&self) -> impl Iterator<Item = &Command> {
+        self.0.values().flatten()
+    }
+}
+
+impl MacroState {
+    // replaces whatever was left of the previous macro
+    pub fn start(&mut self, name: &str, steps: &[Command]) {
+        self.name = name.to_string();
+        self.steps = steps.iter().cloned().collect();
+    }
+
+    // returns the name of the macro if it was running
+    pub fn stop(&mut self) -> Option<String> {
+        if self.steps.is_empty() {
+            return None;
+        }
+        self.steps.clear();
+
+        Some(self.name.clone())
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    // None if there are no more steps, or the next one has to wait
+    pub fn next_step(&mut self) -> Option<Command> {
+        if self.waiting {
+            return None;
+        }
+
+        self.steps.pop_front()
+    }
+
+    pub fn wait(&mut self) {
+        self.waiting = true;
+    }
+
+    // returns whether it was waiting
+    pub fn resume(&mut self) -> bool {
+        std::mem::take(&mut self.waiting)
+    }
+}
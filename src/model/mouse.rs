@@ -0,0 +1,57 @@
+use ratatui::layout::{Position, Rect};
+use std::time::{Duration, Instant};
+
+use crate::constants;
+
+// parts of the screen that react to clicks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Clickable {
+    Queue,
+    LibraryGroups,
+    LibrarySongs,
+    Files,
+    Search,
+    ProgressBar,
+}
+
+#[derive(Debug, Default)]
+pub struct MouseState {
+    areas: Vec<(Rect, Clickable)>, // recorded while rendering
+    last_click: Option<(Instant, Clickable, usize)>,
+}
+
+impl MouseState {
+    // to be called before every render
+    pub fn clear(&mut self) {
+        self.areas.clear();
+    }
+
+    pub fn record(&mut self, area: Rect, clickable: Clickable) {
+        self.areas.push((area, clickable));
+    }
+
+    // the topmost recorded area under the cursor
+    pub fn at(&self, column: u16, row: u16) -> Option<(Rect, Clickable)> {
+        self.areas
+            .iter()
+            .rev()
+            .find(|(area, _)| area.contains(Position::new(column, row)))
+            .copied()
+    }
+
+    // returns true if it's the second click on the same item in a short time
+    pub fn click(&mut self, clickable: Clickable, i: usize) -> bool {
+        let now = Instant::now();
+        let double = self
+            .last_click
+            .is_some_and(|(time, prev_clickable, prev_i)| {
+                prev_clickable == clickable
+                    && prev_i == i
+                    && now - time <= Duration::from_millis(constants::DOUBLE_CLICK_MS)
+            });
+        // a third click starts over
+        self.last_click = (!double).then_some((now, clickable, i));
+
+        double
+    }
+}
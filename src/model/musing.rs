@@ -2,7 +2,7 @@ use anyhow::{Result, anyhow, bail};
 use serde_json::Value;
 use std::fmt::{self, Display, Formatter};
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum PlaybackState {
     #[default]
     Stopped,
@@ -10,7 +10,7 @@ pub enum PlaybackState {
     Paused,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum PlaybackMode {
     #[default]
     Single,
@@ -18,7 +18,7 @@ pub enum PlaybackMode {
     Random,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct MusingSong {
     pub id: u64,
     pub path: String,
@@ -0,0 +1,363 @@
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use regex::{Regex, RegexBuilder};
+use std::{borrow::Cow, cmp::Ordering, collections::HashMap};
+
+use crate::model::search::{SearchItem, SearchMode};
+
+// how a tag's value is compared to the one in the query
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Contains, // tag:value
+    Eq,       // tag:=value
+    Lt,       // tag:<value
+    Le,       // tag:<=value
+    Gt,       // tag:>value
+    Ge,       // tag:>=value
+}
+
+#[derive(Debug)]
+enum Term {
+    Word(String),   // fuzzy matched against the whole item
+    Phrase(String), // "exact phrase", matched as a substring
+    Tag(String, Op, String),
+}
+
+// a parsed search pattern, e.g.: artist:radiohead year:>=2000 -genre:live "exact phrase"
+// every term prefixed with a '-' excludes the items it matches
+// (in the regex mode the whole pattern is a single regex instead)
+#[derive(Debug, Default)]
+pub struct Query {
+    terms: Vec<(bool, Term)>, // (negated, term)
+    words: String,            // all non-negated words, matched together
+    mode: SearchMode,
+    case_sensitive: bool,
+    regex: Option<Regex>, // None if the pattern isn't a valid regex
+}
+
+impl Term {
+    fn parse(token: &str, case_sensitive: bool) -> Self {
+        if let Some(phrase) = token.strip_prefix('"') {
+            let phrase = phrase.strip_suffix('"').unwrap_or(phrase);
+            return Term::Phrase(normalize(phrase, case_sensitive).into_owned());
+        }
+        if let Some((tag, value)) = token.split_once(':')
+            && !tag.is_empty()
+            && tag.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            let (op, value) = [
+                (">=", Op::Ge),
+                ("<=", Op::Le),
+                (">", Op::Gt),
+                ("<", Op::Lt),
+                ("=", Op::Eq),
+            ]
+            .into_iter()
+            .find_map(|(prefix, op)| value.strip_prefix(prefix).map(|v| (op, v)))
+            .unwrap_or((Op::Contains, value));
+            let value = value.trim_matches('"').to_lowercase();
+
+            return Term::Tag(tag_alias(&tag.to_lowercase()).to_string(), op, value);
+        }
+
+        Term::Word(normalize(token, case_sensitive).into_owned())
+    }
+}
+
+impl Query {
+    pub fn parse(pattern: &str, mode: SearchMode, case_sensitive: bool) -> Self {
+        let mut query = Query {
+            mode,
+            case_sensitive,
+            ..Default::default()
+        };
+        if mode == SearchMode::Regex {
+            query.regex = RegexBuilder::new(pattern)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .ok();
+            return query;
+        }
+        let mut words = Vec::new();
+        for token in tokenize(pattern) {
+            let (negated, token) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, token.as_str()),
+            };
+            match Term::parse(token, case_sensitive) {
+                Term::Word(word) if !negated => words.push(word),
+                term => query.terms.push((negated, term)),
+            }
+        }
+        query.words = words.join(" ");
+
+        query
+    }
+
+    // only words, so that every item matched by a longer pattern is matched by this one
+    pub fn is_plain(&self) -> bool {
+        self.terms.is_empty() && self.mode != SearchMode::Regex
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.mode != SearchMode::Regex || self.regex.is_some()
+    }
+
+    // None if the item doesn't match the query
+    // `matcher` is only used in the fuzzy mode
+    pub fn score(&self, matcher: &SkimMatcherV2, item: &SearchItem) -> Option<i64> {
+        if self.mode == SearchMode::Regex {
+            return self
+                .regex
+                .as_ref()
+                .filter(|regex| regex.is_match(&item.repr))
+                .map(|_| 1);
+        }
+        let repr = normalize(&item.repr, self.case_sensitive);
+        for (negated, term) in self.terms.iter() {
+            let matches = match term {
+                Term::Word(word) => repr.contains(word.as_str()),
+                Term::Phrase(phrase) => repr.contains(phrase.as_str()),
+                Term::Tag(tag, op, value) => item
+                    .tags
+                    .iter()
+                    .any(|tags| tag_matches(tags, tag, *op, value)),
+            };
+            if matches == *negated {
+                return None;
+            }
+        }
+        if self.words.is_empty() {
+            // only filters, everything that passed them is equally good
+            return Some(1);
+        }
+
+        match self.mode {
+            SearchMode::Fuzzy => matcher.fuzzy_match(&item.repr, &self.words),
+            _ => self
+                .words
+                .split_whitespace()
+                .all(|word| repr.contains(word))
+                .then_some(1),
+        }
+    }
+
+    // indices of the characters of `text` matched by the query's words and phrases
+    // (every word is matched separately, since `text` is usually only a part of an item)
+    pub fn highlight(&self, text: &str) -> Vec<usize> {
+        let mut indices = Vec::new();
+        match (self.mode, &self.regex) {
+            (SearchMode::Regex, Some(regex)) => {
+                for m in regex.find_iter(text) {
+                    let start = text[..m.start()].chars().count();
+                    indices.extend(start..start + m.as_str().chars().count());
+                }
+            }
+            (SearchMode::Regex, None) => (),
+            (SearchMode::Fuzzy, _) => {
+                let matcher = SkimMatcherV2::default();
+                let matcher = match self.case_sensitive {
+                    true => matcher.respect_case(),
+                    false => matcher.ignore_case(),
+                };
+                for word in self.words.split_whitespace() {
+                    if let Some((_, word_indices)) = matcher.fuzzy_indices(text, word) {
+                        indices.extend(word_indices);
+                    }
+                }
+            }
+            (SearchMode::Substring, _) => {
+                for word in self.words.split_whitespace() {
+                    indices.extend(self.find_all(text, word));
+                }
+            }
+        }
+        for (negated, term) in self.terms.iter() {
+            if let (false, Term::Phrase(phrase)) = (negated, term) {
+                indices.extend(self.find_all(text, phrase));
+            }
+        }
+        indices.sort_unstable();
+        indices.dedup();
+
+        indices
+    }
+
+    // indices of the characters of all occurences of `needle` in `text`
+    fn find_all(&self, text: &str, needle: &str) -> Vec<usize> {
+        let haystack = normalize(text, self.case_sensitive);
+        // lowercasing can change the number of characters, in that case don't bother
+        if needle.is_empty() || haystack.chars().count() != text.chars().count() {
+            return Vec::new();
+        }
+
+        haystack
+            .match_indices(needle)
+            .flat_map(|(start, _)| {
+                let start = haystack[..start].chars().count();
+                start..start + needle.chars().count()
+            })
+            .collect()
+    }
+}
+
+fn normalize(s: &str, case_sensitive: bool) -> Cow<'_, str> {
+    match case_sensitive {
+        true => Cow::Borrowed(s),
+        false => Cow::Owned(s.to_lowercase()),
+    }
+}
+
+// splits on whitespace, except inside of quotes
+fn tokenize(pattern: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in pattern.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                token.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+fn tag_alias(tag: &str) -> &str {
+    match tag {
+        "year" => "date",
+        "title" => "tracktitle",
+        "track" => "tracknumber",
+        "disc" => "discnumber",
+        other => other,
+    }
+}
+
+fn tag_matches(tags: &HashMap<String, String>, tag: &str, op: Op, value: &str) -> bool {
+    let Some(actual) = tags.get(tag) else {
+        return false;
+    };
+    let actual = unidecode::unidecode(actual).to_lowercase();
+    if op == Op::Contains {
+        return actual.contains(value);
+    }
+    // compare numerically if possible, so that e.g. "2003-05-01" >= "2000" works
+    let ordering = match (leading_number(&actual), leading_number(value)) {
+        (Some(lhs), Some(rhs)) => lhs.partial_cmp(&rhs).unwrap_or(Ordering::Equal),
+        _ => actual.as_str().cmp(value),
+    };
+
+    match op {
+        Op::Eq => ordering == Ordering::Equal,
+        Op::Lt => ordering == Ordering::Less,
+        Op::Le => ordering != Ordering::Greater,
+        Op::Gt => ordering == Ordering::Greater,
+        Op::Ge => ordering != Ordering::Less,
+        Op::Contains => unreachable!(),
+    }
+}
+
+fn leading_number(s: &str) -> Option<f64> {
+    let end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+
+    s[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn item(repr: &str, tags: &[(&str, &str)]) -> SearchItem {
+        let tags = tags
+            .iter()
+            .map(|(tag, value)| (tag.to_string(), value.to_string()))
+            .collect();
+        SearchItem {
+            repr: repr.to_string(),
+            tags: vec![Arc::new(tags)],
+        }
+    }
+
+    fn matches(pattern: &str, item: &SearchItem) -> bool {
+        let query = Query::parse(pattern, SearchMode::Substring, false);
+        query.score(&SkimMatcherV2::default(), item).is_some()
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokenize(r#"  a "b c"  -d:"e f" "#),
+            ["a", r#""b c""#, r#"-d:"e f""#]
+        );
+    }
+
+    #[test]
+    fn terms() {
+        let query = Query::parse(
+            r#"Foo -bar year:>=2000 Title:=x "Two Words" -"#,
+            SearchMode::Fuzzy,
+            false,
+        );
+        assert_eq!(query.words, "foo -");
+        assert!(!query.is_plain());
+        assert!(matches!(
+            &query.terms[..],
+            [
+                (true, Term::Word(bar)),
+                (false, Term::Tag(date, Op::Ge, year)),
+                (false, Term::Tag(title, Op::Eq, x)),
+                (false, Term::Phrase(phrase)),
+            ] if bar == "bar"
+                && date == "date"
+                && year == "2000"
+                && title == "tracktitle"
+                && x == "x"
+                && phrase == "two words"
+        ));
+        assert!(Query::parse("just words", SearchMode::Fuzzy, false).is_plain());
+    }
+
+    #[test]
+    fn tag_filters() {
+        let song = item(
+            "Karma Police Radiohead",
+            &[
+                ("artist", "Radiohead"),
+                ("date", "1997-05-21"),
+                ("tracknumber", "6"),
+            ],
+        );
+        assert!(matches("artist:radio", &song));
+        assert!(!matches("artist:=radio", &song));
+        assert!(matches("artist:=radiohead", &song));
+        assert!(matches("year:>=1990 year:<2000", &song));
+        assert!(!matches("year:>1997", &song));
+        assert!(matches("track:<=6 track:>5", &song));
+        assert!(!matches("genre:rock", &song));
+        assert!(matches("-genre:rock", &song));
+        assert!(!matches("-artist:radiohead", &song));
+        assert!(matches("police -\"karma chameleon\"", &song));
+        assert!(!matches("police -karma", &song));
+    }
+
+    #[test]
+    fn invalid_regex() {
+        let query = Query::parse("(unclosed", SearchMode::Regex, false);
+        assert!(!query.is_valid());
+        assert!(query.highlight("(unclosed").is_empty());
+        assert!(Query::parse("^a.c$", SearchMode::Regex, false).is_valid());
+    }
+}
@@ -1,8 +1,14 @@
+use anyhow::{Result, anyhow, bail};
 use ratatui::widgets::TableState;
+use std::{fs, path::PathBuf};
 
-use crate::model::{
-    common::{Scroll, SongGroup},
-    search::{Search, SearchState},
+use crate::{
+    constants,
+    model::{
+        common::{self, Scroll, SongGroup, scroll_list},
+        musing::MusingSong,
+        search::{Search, SearchItem},
+    },
 };
 
 #[derive(Debug, Default)]
@@ -11,45 +17,29 @@ pub struct QueueState {
     pub group: SongGroup,
     pub queue_tags: Vec<String>, // tags to be displayed to the user
     pub search: Search,
+    pub rows: Vec<Vec<String>>, // values of `queue_tags` and the duration of every song, formatted
+    pub total_duration: u64,
 }
 
 impl Scroll for QueueState {
     fn scroll(&mut self, delta: i32) {
-        if self.group.is_empty() {
-            return;
-        }
-        let u_delta = delta.unsigned_abs() as usize;
-        let n_rows = self.group.len();
-        match self.state.selected() {
-            Some(r) => {
-                if delta < 0 {
-                    if r >= u_delta {
-                        self.state.scroll_up_by(u_delta as u16);
-                    } else {
-                        self.state.select(Some(n_rows - (u_delta - r)));
-                    }
-                } else if r + u_delta < n_rows {
-                    self.state.scroll_down_by(u_delta as u16);
-                } else {
-                    self.state.select(Some(u_delta - (n_rows - r)));
-                }
-            }
-            None => self.state.select_first(),
-        };
+        let n_rows = self.search.n_rows(self.group.len());
+        scroll_list(&mut self.state, n_rows, delta);
     }
 
     fn scroll_to_top(&mut self) {
-        if self.group.is_empty() {
+        if self.search.n_rows(self.group.len()) == 0 {
             return;
         }
         self.state.select_first();
     }
 
     fn scroll_to_bottom(&mut self) {
-        if self.group.is_empty() {
+        let n_rows = self.search.n_rows(self.group.len());
+        if n_rows == 0 {
             return;
         }
-        self.state.select(Some(self.group.len().saturating_sub(1)));
+        self.state.select(Some(n_rows - 1));
     }
 }
 
@@ -60,19 +50,21 @@ impl QueueState {
             group: SongGroup::default(),
             queue_tags,
             search: Search::default(),
+            rows: Vec::new(),
+            total_duration: 0,
         }
     }
 
     pub fn search_on(&mut self) {
         self.scroll_to_top();
-        self.search.on(self.metadata_to_repr());
+        self.search.on(self.metadata_to_items());
     }
 
     pub fn unordered_selected(&self) -> Option<usize> {
-        self.state.selected().map(|i| self.search.real_i(i))
+        self.state.selected().and_then(|i| self.search.real_i(i))
     }
 
-    pub fn metadata_to_repr(&self) -> Vec<String> {
+    pub fn metadata_to_items(&self) -> Vec<SearchItem> {
         self.group
             .metadata
             .iter()
@@ -85,20 +77,64 @@ impl QueueState {
                     }
                 }
 
-                unidecode::unidecode(&repr)
+                SearchItem {
+                    repr: unidecode::unidecode(&repr),
+                    tags: vec![m.clone()],
+                }
             })
             .collect()
     }
 
-    pub fn ordered_group(&self) -> SongGroup {
-        let search = &self.search;
-        match search.state {
-            SearchState::Off => self.group.clone(),
-            _ => {
-                // clone not to hold up the guard
-                let order = { search.result.read().unwrap().clone() };
-                self.group.new_ordered(&order)
-            }
-        }
+    // to be called whenever the songs or their metadata change,
+    // so that rendering doesn't have to format every song on every frame
+    pub fn update_rows(&mut self) {
+        let mut total_duration = 0;
+        self.rows = self
+            .group
+            .paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let m = self.group.metadata.get(i);
+                let get = |tag: &str| m.and_then(|m| m.get(tag)).map(|s| s.as_str());
+                let mut row: Vec<_> = self
+                    .queue_tags
+                    .iter()
+                    .map(|tag| match tag.as_str() {
+                        "tracktitle" => get(tag).unwrap_or(path).to_string(),
+                        _ => get(tag).unwrap_or(constants::UNKNOWN).to_string(),
+                    })
+                    .collect();
+                let duration = get("duration")
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .unwrap_or_default();
+                total_duration += duration;
+                row.push(common::format_time(duration));
+
+                row
+            })
+            .collect();
+        self.total_duration = total_duration;
     }
 }
+
+// writes the queue to an m3u playlist in the data directory, returns its path
+pub fn save_playlist(name: &str, songs: &[MusingSong]) -> Result<PathBuf> {
+    if name.contains(['/', '\\']) {
+        bail!("invalid playlist name `{}`", name);
+    }
+    let dir = dirs::data_dir()
+        .ok_or(anyhow!("could not find the data directory"))?
+        .join(constants::DEFAULT_CONFIG_DIR)
+        .join(constants::PLAYLIST_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.m3u", name));
+    let mut playlist = String::from("#EXTM3U\n");
+    for song in songs {
+        playlist.push_str(&song.path);
+        playlist.push('\n');
+    }
+    fs::write(&path, playlist)?;
+
+    Ok(path)
+}
@@ -1,15 +1,53 @@
 use fuzzy_matcher::skim::SkimMatcherV2;
 use rayon::prelude::*;
 use std::{
-    sync::{Arc, RwLock, mpsc as std_chan},
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    ops::Range,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self as std_chan, RecvTimeoutError},
+    },
     thread,
+    time::Duration,
 };
+use strum_macros::EnumString;
 use tui_input::Input as TuiInput;
 
+use crate::{constants, model::query::Query};
+
+// something that can be searched for: its textual representation
+// and the tags of the song(s) it stands for (used by tag filters like `artist:abba`)
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchItem {
+    pub repr: String,
+    pub tags: Vec<Arc<HashMap<String, String>>>,
+}
+
+// the ordering of the items, published by the search thread all at once
+#[derive(Clone, Debug, Default)]
+pub struct SearchResult {
+    pub generation: u64, // of the newest message that this result takes into account
+    pub order: Vec<usize>,
+    pub n_matches: usize, // the matching items come first in `order`
+}
+
 #[derive(Debug)]
 pub enum SearchMessage {
     NewPattern(String),
-    NewList(Vec<String>),
+    NewList(Vec<SearchItem>),
+    SetMode(SearchMode, bool), // (mode, case sensitive)
+}
+
+// how the words of a pattern are matched against items
+#[derive(Clone, Copy, Debug, Default, EnumString, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+pub enum SearchMode {
+    #[default]
+    Fuzzy,
+    Substring,
+    Regex,
 }
 
 #[derive(Debug, Default)]
@@ -20,90 +58,512 @@ pub enum SearchState {
     Idle,
 }
 
+// lists that keep separate search histories
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SearchContext {
+    Queue,
+    LibraryGroups,
+    LibrarySongs,
+    Files,
+    Global,
+    Help,
+}
+
+// patterns searched for in one context, the newest one last
+#[derive(Debug, Default)]
+pub struct SearchHistory {
+    entries: Vec<String>,
+    cursor: Option<usize>, // the entry being recalled
+    draft: String,         // what was typed before recalling anything
+}
+
 #[derive(Debug, Default)]
 pub struct Search {
-    pub tx: Option<std_chan::Sender<SearchMessage>>,
-    pub result: Arc<RwLock<Vec<usize>>>,
+    pub tx: Option<std_chan::Sender<(u64, SearchMessage)>>,
+    pub result: Arc<RwLock<Arc<SearchResult>>>,
+    pub generation: Arc<AtomicU64>, // of the newest message sent to the search thread
     pub input: TuiInput,
     pub state: SearchState,
+    pub query: Query, // the current pattern, parsed (used for highlighting)
+    pub filter: bool, // whether items that don't match are hidden
+    pub mode: SearchMode,
+    pub case_sensitive: bool,
+}
+
+impl SearchMode {
+    pub fn next(self) -> Self {
+        match self {
+            SearchMode::Fuzzy => SearchMode::Substring,
+            SearchMode::Substring => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Fuzzy,
+        }
+    }
+}
+
+impl Display for SearchMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SearchMode::Fuzzy => "fuzzy",
+            SearchMode::Substring => "substring",
+            SearchMode::Regex => "regex",
+        };
+
+        write!(f, "{}", s)
+    }
 }
 
 impl Search {
-    pub fn on(&mut self, list: Vec<String>) {
+    pub fn on(&mut self, list: Vec<SearchItem>) {
         // ensure that the old search thread ends
         self.off();
         let (tx, rx) = std_chan::channel();
-        let result = Arc::new(RwLock::new((0..list.len()).collect()));
+        let result = Arc::new(RwLock::new(Arc::new(SearchResult {
+            generation: 0,
+            order: (0..list.len()).collect(),
+            n_matches: list.len(),
+        })));
+        let generation = Arc::new(AtomicU64::new(0));
         self.tx = Some(tx);
         self.result = Arc::clone(&result);
+        self.generation = Arc::clone(&generation);
         self.state = SearchState::On;
-        run(list, rx, result);
+        run(list, rx, result, generation, self.filter);
+        self.mode_update();
     }
 
     pub fn off(&mut self) {
         let _ = self.tx.take();
         self.input = TuiInput::default();
         self.state = SearchState::Off;
+        self.query = Query::default();
     }
 
     pub fn idle(&mut self) {
         self.state = SearchState::Idle;
     }
 
-    pub fn pattern_update(&self, pattern: String) {
-        if let Some(tx) = &self.tx {
-            let _ = tx.send(SearchMessage::NewPattern(pattern));
+    // go back to typing the pattern of an idle search
+    pub fn resume(&mut self) {
+        self.state = SearchState::On;
+    }
+
+    pub fn set_pattern(&mut self, pattern: String) {
+        self.input = TuiInput::new(pattern.clone());
+        self.pattern_update(pattern);
+    }
+
+    // inserts `text` at the cursor in one step (e.g. when it's pasted)
+    pub fn insert(&mut self, text: &str) {
+        let cursor = self.input.cursor();
+        let value = self.input.value();
+        let split = value
+            .char_indices()
+            .nth(cursor)
+            .map_or(value.len(), |(i, _)| i);
+        // the pattern is a single line
+        let text = text.replace(['\r', '\n', '\t'], " ");
+        let new_value = format!("{}{}{}", &value[..split], text, &value[split..]);
+        self.input = TuiInput::new(new_value).with_cursor(cursor + text.chars().count());
+    }
+
+    pub fn cycle_mode(&mut self) {
+        self.mode = self.mode.next();
+        self.mode_update();
+    }
+
+    pub fn toggle_case_sensitive(&mut self) {
+        self.case_sensitive = !self.case_sensitive;
+        self.mode_update();
+    }
+
+    fn mode_update(&mut self) {
+        self.query = Query::parse(self.input.value(), self.mode, self.case_sensitive);
+        self.send(SearchMessage::SetMode(self.mode, self.case_sensitive));
+    }
+
+    pub fn pattern_update(&mut self, pattern: String) {
+        self.query = Query::parse(&pattern, self.mode, self.case_sensitive);
+        self.send(SearchMessage::NewPattern(pattern));
+    }
+
+    // the list is only built if there's a search running
+    pub fn list_update(&self, list: impl FnOnce() -> Vec<SearchItem>) {
+        if self.tx.is_some() {
+            self.send(SearchMessage::NewList(list()));
         }
     }
 
-    pub fn list_update(&self, list: Vec<String>) {
+    fn send(&self, msg: SearchMessage) {
         if let Some(tx) = &self.tx {
-            let _ = tx.send(SearchMessage::NewList(list));
+            let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+            let _ = tx.send((generation, msg));
+        }
+    }
+
+    // a snapshot of the latest result, which is never modified in place
+    pub fn result(&self) -> Arc<SearchResult> {
+        Arc::clone(&self.result.read().unwrap())
+    }
+
+    // whether the search thread is still working on the newest pattern/list
+    pub fn is_pending(&self) -> bool {
+        self.tx.is_some() && self.result().generation != self.generation.load(Ordering::Relaxed)
+    }
+
+    // None if there's no such row (e.g. when every item got filtered out)
+    pub fn real_i(&self, i: usize) -> Option<usize> {
+        match &self.tx {
+            Some(_) => self.result().order.get(i).copied(),
+            None => Some(i),
         }
     }
 
-    pub fn real_i(&self, i: usize) -> usize {
+    // how many rows are displayed out of `n_items`
+    pub fn n_rows(&self, n_items: usize) -> usize {
+        match &self.tx {
+            Some(_) => self.result().order.len(),
+            None => n_items,
+        }
+    }
+
+    // the real indices of the items displayed in `rows`
+    pub fn real_range(&self, rows: Range<usize>) -> Vec<usize> {
         match &self.tx {
             Some(_) => {
-                let order = self.result.read().unwrap();
-                (*order).get(i).copied().unwrap_or_default()
+                let order = &self.result().order;
+                let end = rows.end.min(order.len());
+                order[rows.start.min(end)..end].to_vec()
             }
-            None => i,
+            None => rows.collect(),
+        }
+    }
+
+    pub fn n_matches(&self) -> usize {
+        self.result().n_matches
+    }
+
+    // the row of the next (or previous) match after `selected`, wraps around
+    pub fn next_match(&self, selected: Option<usize>, delta: isize) -> Option<usize> {
+        let n = self.n_matches() as isize;
+        if n == 0 {
+            return None;
+        }
+        let i = match selected {
+            Some(i) if (i as isize) < n => i as isize + delta,
+            _ if delta > 0 => 0,
+            _ => n - 1,
+        };
+
+        Some(i.rem_euclid(n) as usize)
+    }
+}
+
+impl SearchHistory {
+    pub fn push(&mut self, pattern: &str) {
+        self.cursor = None;
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return;
+        }
+        self.entries.retain(|entry| entry != pattern);
+        self.entries.push(pattern.to_string());
+        if self.entries.len() > constants::SEARCH_HISTORY_LEN {
+            self.entries.remove(0);
+        }
+    }
+
+    // to be called when a new search starts
+    pub fn reset(&mut self) {
+        self.cursor = None;
+    }
+
+    // moves `delta` entries back (if negative) or forward in the history,
+    // going past the newest entry gives back the pattern that was typed before
+    pub fn recall(&mut self, delta: isize, current: &str) -> String {
+        let n = self.entries.len() as isize;
+        if self.cursor.is_none() {
+            self.draft = current.to_string();
+        }
+        let i = (self.cursor.map_or(n, |i| i as isize) + delta).clamp(0, n);
+        self.cursor = (i < n).then_some(i as usize);
+
+        match self.cursor {
+            Some(i) => self.entries[i].clone(),
+            None => self.draft.clone(),
         }
     }
 }
 
-fn compute_ordering(matcher: &SkimMatcherV2, list: &[String], pattern: &str) -> Vec<usize> {
-    let mut scores: Vec<_> = list
-        .par_iter()
-        .enumerate()
+// returns the ordering and the number of matching items,
+// only `candidates` are scored if given (all the other items are known not to match)
+// None if the computation got cancelled
+fn compute_ordering(
+    matcher: &SkimMatcherV2,
+    list: &[SearchItem],
+    query: &Query,
+    filter: bool,
+    candidates: Option<&[usize]>,
+    cancelled: &(dyn Fn() -> bool + Sync),
+) -> Option<(Vec<usize>, usize)> {
+    let score = |i: usize| {
+        if cancelled() {
+            return Err(());
+        }
         // negative sign because we want to sort descending
-        .map(|(i, s)| (-matcher.fuzzy(s, pattern, false).unwrap_or_default().0, i))
-        .collect();
+        Ok(query
+            .score(matcher, &list[i])
+            .filter(|&score| score > 0)
+            .map(|score| (-score, i)))
+    };
+    let scores: Result<Vec<_>, _> = match candidates {
+        Some(candidates) => candidates.par_iter().map(|&i| score(i)).collect(),
+        None => (0..list.len()).into_par_iter().map(score).collect(),
+    };
+    let mut scores: Vec<_> = scores.ok()?.into_iter().flatten().collect();
     scores.par_sort_unstable();
+    let n_matches = scores.len();
+    let mut order: Vec<_> = scores.into_iter().map(|(_, i)| i).collect();
+    if !filter {
+        // the items that don't match go to the bottom, in their original order
+        let mut matched = vec![false; list.len()];
+        for &i in order.iter() {
+            matched[i] = true;
+        }
+        order.extend((0..list.len()).filter(|&i| !matched[i]));
+    }
+
+    Some((order, n_matches))
+}
 
-    scores.into_iter().map(|(_, i)| i).collect()
+// the items that `pattern` can match, if it only narrows down the last plain pattern
+// (anything else, e.g. a tag filter or a regex, has to go through the whole list)
+fn narrowed_candidates<'a>(
+    narrowable: Option<&'a (String, Vec<usize>)>,
+    query: &Query,
+    pattern: &str,
+) -> Option<&'a [usize]> {
+    narrowable
+        .filter(|(prev, _)| query.is_plain() && pattern.starts_with(prev.as_str()))
+        .map(|(_, matches)| matches.as_slice())
 }
 
 pub fn run(
-    mut list: Vec<String>,
-    rx: std_chan::Receiver<SearchMessage>,
-    result: Arc<RwLock<Vec<usize>>>,
+    mut list: Vec<SearchItem>,
+    rx: std_chan::Receiver<(u64, SearchMessage)>,
+    result: Arc<RwLock<Arc<SearchResult>>>,
+    generation: Arc<AtomicU64>,
+    filter: bool,
 ) {
     thread::spawn(move || {
-        let matcher = SkimMatcherV2::default().ignore_case();
+        let debounce = Duration::from_millis(constants::SEARCH_DEBOUNCE_MS);
+        let mut matcher = SkimMatcherV2::default().ignore_case();
         let mut pattern = String::new();
+        let (mut mode, mut case_sensitive) = (SearchMode::default(), false);
+        // the last plain pattern and the items it matched,
+        // a longer pattern can only match some of these items
+        let mut narrowable: Option<(String, Vec<usize>)> = None;
         while let Ok(msg) = rx.recv() {
-            match msg {
-                SearchMessage::NewPattern(new_pattern) => {
-                    pattern = unidecode::unidecode(&new_pattern);
-                    *result.write().unwrap() = compute_ordering(&matcher, &list, &pattern);
+            let mut messages = vec![msg];
+            // wait for a pause in typing, so that only the newest pattern gets scored
+            loop {
+                match rx.recv_timeout(debounce) {
+                    Ok(msg) => messages.push(msg),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
                 }
-                SearchMessage::NewList(new_list) => {
-                    list = new_list;
-                    *result.write().unwrap() = compute_ordering(&matcher, &list, &pattern);
+            }
+            let mut target = 0;
+            let mut changed = false;
+            for (msg_generation, msg) in messages {
+                target = msg_generation;
+                match msg {
+                    SearchMessage::NewPattern(new_pattern) => {
+                        pattern = unidecode::unidecode(&new_pattern);
+                        changed = true;
+                    }
+                    // the metadata gets refreshed often, usually without any changes
+                    SearchMessage::NewList(new_list) if new_list == list => (),
+                    SearchMessage::NewList(new_list) => {
+                        list = new_list;
+                        narrowable = None;
+                        changed = true;
+                    }
+                    SearchMessage::SetMode(new_mode, new_case_sensitive) => {
+                        (mode, case_sensitive) = (new_mode, new_case_sensitive);
+                        matcher = match case_sensitive {
+                            true => SkimMatcherV2::default().respect_case(),
+                            false => SkimMatcherV2::default().ignore_case(),
+                        };
+                        narrowable = None;
+                        changed = true;
+                    }
                 }
             }
+            if !changed {
+                let unchanged = SearchResult {
+                    generation: target,
+                    ..(**result.read().unwrap()).clone()
+                };
+                *result.write().unwrap() = Arc::new(unchanged);
+                continue;
+            }
+
+            let query = Query::parse(&pattern, mode, case_sensitive);
+            let candidates = narrowed_candidates(narrowable.as_ref(), &query, &pattern);
+            let cancelled = || generation.load(Ordering::Relaxed) != target;
+            let Some((order, n_matches)) =
+                compute_ordering(&matcher, &list, &query, filter, candidates, &cancelled)
+            else {
+                // there's a newer message waiting already
+                continue;
+            };
+            narrowable = query
+                .is_plain()
+                .then(|| (pattern.clone(), order[..n_matches].to_vec()));
+            // the new result is swapped in whole, so readers never see a partial one
+            *result.write().unwrap() = Arc::new(SearchResult {
+                generation: target,
+                order,
+                n_matches,
+            });
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const SONGS: [&str; 8] = [
+        "Bohemian Rhapsody Queen",
+        "Blackbird The Beatles",
+        "Be My Baby The Ronettes",
+        "Beat It Michael Jackson",
+        "Here Comes the Sun The Beatles",
+        "Heroes David Bowie",
+        "Hey Jude The Beatles",
+        "Bela Lugosi's Dead Bauhaus",
+    ];
+
+    fn items() -> Vec<SearchItem> {
+        SONGS
+            .iter()
+            .map(|song| SearchItem {
+                repr: song.to_string(),
+                tags: Vec::new(),
+            })
+            .collect()
+    }
+
+    fn ordering(
+        pattern: &str,
+        mode: SearchMode,
+        filter: bool,
+        candidates: Option<&[usize]>,
+    ) -> (Vec<usize>, usize) {
+        let query = Query::parse(pattern, mode, false);
+        let matcher = SkimMatcherV2::default().ignore_case();
+        compute_ordering(&matcher, &items(), &query, filter, candidates, &|| false).unwrap()
+    }
+
+    // waits for the search thread to catch up with the newest message
+    fn settled(search: &Search) -> Arc<SearchResult> {
+        let start = Instant::now();
+        while search.is_pending() {
+            assert!(start.elapsed() < Duration::from_secs(5), "the search hung");
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        search.result()
+    }
+
+    #[test]
+    fn narrowing_gives_the_same_results() {
+        for mode in [SearchMode::Fuzzy, SearchMode::Substring] {
+            for filter in [true, false] {
+                let mut prev_matches: Option<Vec<usize>> = None;
+                for pattern in ["b", "be", "bea", "beat", "beatl", "beatles"] {
+                    let full = ordering(pattern, mode, filter, None);
+                    if let Some(prev_matches) = prev_matches {
+                        let narrowed = ordering(pattern, mode, filter, Some(&prev_matches));
+                        assert_eq!(narrowed, full, "{} ({})", pattern, mode);
+                    }
+                    prev_matches = Some(full.0[..full.1].to_vec());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_plain_patterns_narrow() {
+        let narrowable = ("bea".to_string(), vec![1, 3]);
+        let candidates = |pattern: &str, mode: SearchMode| {
+            let query = Query::parse(pattern, mode, false);
+            narrowed_candidates(Some(&narrowable), &query, pattern).map(|c| c.to_vec())
+        };
+        assert_eq!(candidates("beat", SearchMode::Fuzzy), Some(vec![1, 3]));
+        assert_eq!(
+            candidates("bea jude", SearchMode::Substring),
+            Some(vec![1, 3])
+        );
+        // not an extension of the last pattern
+        assert_eq!(candidates("be", SearchMode::Fuzzy), None);
+        assert_eq!(candidates("hey", SearchMode::Fuzzy), None);
+        // filters, phrases, exclusions and regexes always search the whole list
+        assert_eq!(candidates("bea artist:queen", SearchMode::Fuzzy), None);
+        assert_eq!(candidates("bea -jude", SearchMode::Fuzzy), None);
+        assert_eq!(candidates("bea \"the\"", SearchMode::Fuzzy), None);
+        assert_eq!(candidates("bea|hey", SearchMode::Regex), None);
+        let query = Query::parse("beat", SearchMode::Fuzzy, false);
+        assert_eq!(narrowed_candidates(None, &query, "beat"), None);
+    }
+
+    #[test]
+    fn cancelled_searches_give_nothing() {
+        let query = Query::parse("be", SearchMode::Fuzzy, false);
+        let matcher = SkimMatcherV2::default();
+        assert_eq!(
+            compute_ordering(&matcher, &items(), &query, true, None, &|| true),
+            None
+        );
+    }
+
+    #[test]
+    fn the_worker_keeps_up_with_the_pattern() {
+        let mut search = Search {
+            filter: true,
+            ..Default::default()
+        };
+        search.on(items());
+        // typed quickly, only the newest pattern has to be scored
+        for pattern in ["b", "be", "bea", "beat"] {
+            search.pattern_update(pattern.to_string());
+        }
+        let result = settled(&search);
+        assert_eq!(result.generation, search.generation.load(Ordering::Relaxed));
+        let full = ordering("beat", SearchMode::Fuzzy, true, None);
+        assert_eq!((result.order.clone(), result.n_matches), full);
+
+        // narrowed down from the plain pattern above
+        search.pattern_update("beatles".to_string());
+        let result = settled(&search);
+        let full = ordering("beatles", SearchMode::Fuzzy, true, None);
+        assert_eq!((result.order.clone(), result.n_matches), full);
+
+        // regexes match things the plain pattern before didn't
+        search.cycle_mode();
+        search.cycle_mode();
+        search.pattern_update("beatles|queen".to_string());
+        let result = settled(&search);
+        assert_eq!(result.n_matches, 4);
+        let full = ordering("beatles|queen", SearchMode::Regex, true, None);
+        assert_eq!((result.order.clone(), result.n_matches), full);
+
+        // an unchanged list doesn't trigger a new search, but still catches up
+        search.list_update(items);
+        let unchanged = settled(&search);
+        assert_eq!(unchanged.order, result.order);
+        search.off();
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use base64::prelude::*;
+use ratatui::widgets::TableState;
+use std::{
+    collections::HashMap,
+    io::{Write, stdout},
+    path::Path,
+};
+
+use crate::model::common::{self, Scroll, scroll_list};
+
+// every tag of one song, as reported by musing
+#[derive(Debug, Default)]
+pub struct SongInfoState {
+    pub state: TableState,
+    pub path: String,
+    pub rows: Vec<(String, String)>,
+}
+
+impl Scroll for SongInfoState {
+    fn scroll(&mut self, delta: i32) {
+        scroll_list(&mut self.state, self.rows.len(), delta);
+    }
+
+    fn scroll_to_top(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        self.state.select_first();
+    }
+
+    fn scroll_to_bottom(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        self.state.select(Some(self.rows.len().saturating_sub(1)));
+    }
+}
+
+impl SongInfoState {
+    pub fn new(path: String, mut tags: HashMap<String, String>) -> Self {
+        let mut rows = vec![("path".to_string(), path.clone())];
+        if let Some(duration) = tags.remove("duration") {
+            let formatted = duration
+                .parse::<u64>()
+                .map(common::format_time)
+                .unwrap_or(duration);
+            rows.push(("duration".into(), formatted));
+        }
+        if let Some(ext) = Path::new(&path).extension() {
+            rows.push(("format".into(), ext.to_string_lossy().to_uppercase()));
+        }
+        let mut tags: Vec<_> = tags.into_iter().collect();
+        tags.sort_unstable();
+        rows.extend(tags);
+
+        Self {
+            state: TableState::default().with_selected(0),
+            path,
+            rows,
+        }
+    }
+
+    pub fn selected_row(&self) -> Option<&(String, String)> {
+        self.state.selected().and_then(|i| self.rows.get(i))
+    }
+}
+
+// uses the OSC 52 escape sequence, so that it works over ssh as well
+pub fn copy_to_clipboard(s: &str) -> Result<()> {
+    let mut stdout = stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", BASE64_STANDARD.encode(s))?;
+    stdout.flush()?;
+
+    Ok(())
+}
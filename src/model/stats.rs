@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{constants, model::library::LibraryChildState};
+
+// songs sharing a tag value (e.g. all songs of one genre)
+#[derive(Debug, Default)]
+pub struct Tally {
+    pub name: String,
+    pub tracks: u64,
+    pub duration: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct LibraryStats {
+    pub n_artists: usize,
+    pub n_albums: usize,
+    pub n_songs: usize,
+    pub total_duration: u64,
+    pub genres: Vec<Tally>,
+    pub years: Vec<Tally>,
+    pub artists: Vec<Tally>,
+    pub albums: Vec<Tally>,
+    pub decades: Vec<(u64, u64)>, // (decade, number of songs)
+}
+
+#[derive(Debug, Default)]
+pub struct StatsState {
+    pub stats: LibraryStats,
+    pub by_duration: bool, // whether the top lists are ordered by duration or by track count
+}
+
+impl LibraryStats {
+    pub fn new(children: &[LibraryChildState]) -> Self {
+        let mut stats = Self::default();
+        let mut genres: HashMap<&str, Tally> = HashMap::new();
+        let mut years: HashMap<&str, Tally> = HashMap::new();
+        let mut artists: HashMap<&str, Tally> = HashMap::new();
+        let mut albums: HashMap<(&str, &str), Tally> = HashMap::new();
+        let mut decades: BTreeMap<u64, u64> = BTreeMap::new();
+        for meta in children
+            .iter()
+            .flat_map(|child| child.group.metadata.iter())
+        {
+            let get = |tag: &str| meta.get(tag).map(|s| s.as_str());
+            let duration = get("duration")
+                .and_then(|s| s.parse::<u64>().ok())
+                .unwrap_or_default();
+            let year = get("date").and_then(|date| date.get(..4));
+            let artist = get("artist").unwrap_or(constants::UNKNOWN);
+            let album_artist = get("albumartist").unwrap_or(artist);
+            let album = get("album").unwrap_or(constants::UNKNOWN);
+            stats.n_songs += 1;
+            stats.total_duration += duration;
+
+            let tallies = [
+                (&mut genres, get("genre").unwrap_or(constants::UNKNOWN)),
+                (&mut years, year.unwrap_or(constants::UNKNOWN)),
+                (&mut artists, artist),
+            ];
+            for (tallies, name) in tallies {
+                let tally = tallies.entry(name).or_default();
+                tally.tracks += 1;
+                tally.duration += duration;
+            }
+            let tally = albums.entry((album_artist, album)).or_default();
+            tally.tracks += 1;
+            tally.duration += duration;
+            if let Some(year) = year.and_then(|y| y.parse::<u64>().ok()) {
+                *decades.entry(year / 10 * 10).or_default() += 1;
+            }
+        }
+
+        stats.n_artists = artists.len();
+        stats.n_albums = albums.len();
+        stats.genres = into_tallies(genres, |name| name.to_string());
+        stats.years = into_tallies(years, |name| name.to_string());
+        stats.artists = into_tallies(artists, |name| name.to_string());
+        stats.albums = into_tallies(albums, |(artist, album)| format!("{} - {}", album, artist));
+        stats.decades = decades.into_iter().collect();
+
+        stats
+    }
+}
+
+impl StatsState {
+    pub fn update(&mut self, children: &[LibraryChildState]) {
+        self.stats = LibraryStats::new(children);
+        self.sort();
+    }
+
+    pub fn toggle_order(&mut self) {
+        self.by_duration = !self.by_duration;
+        self.sort();
+    }
+
+    fn sort(&mut self) {
+        let stats = &mut self.stats;
+        for tallies in [
+            &mut stats.genres,
+            &mut stats.years,
+            &mut stats.artists,
+            &mut stats.albums,
+        ] {
+            if self.by_duration {
+                tallies.sort_by(|lhs, rhs| {
+                    (rhs.duration.cmp(&lhs.duration)).then_with(|| lhs.name.cmp(&rhs.name))
+                });
+            } else {
+                tallies.sort_by(|lhs, rhs| {
+                    (rhs.tracks.cmp(&lhs.tracks)).then_with(|| lhs.name.cmp(&rhs.name))
+                });
+            }
+        }
+    }
+}
+
+fn into_tallies<K>(map: HashMap<K, Tally>, name: impl Fn(K) -> String) -> Vec<Tally> {
+    map.into_iter()
+        .map(|(key, tally)| Tally {
+            name: name(key),
+            ..tally
+        })
+        .collect()
+}
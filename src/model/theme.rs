@@ -13,6 +13,9 @@ pub struct Theme {
     pub total_duration: Style,
     pub progress_bar_done: Style,
     pub progress_bar_rest: Style,
+    pub directory: Style,
+    pub tag_name: Style,
+    pub search_highlight: Style,
 }
 
 impl Default for Theme {
@@ -29,6 +32,9 @@ impl Default for Theme {
             total_duration: Style::default().fg(Color::Cyan),
             progress_bar_done: Style::default().fg(Color::Cyan),
             progress_bar_rest: Style::default(),
+            directory: Style::default().fg(Color::Yellow).bold(),
+            tag_name: Style::default().fg(Color::Cyan),
+            search_highlight: Style::default().fg(Color::Red).bold(),
         }
     }
 }
@@ -67,6 +73,15 @@ impl TryFrom<Table> for Theme {
                 ("progress_bar_rest", TomlValue::Table(progress_bar_rest)) => {
                     theme.progress_bar_rest = try_from_table(progress_bar_rest)?;
                 }
+                ("directory", TomlValue::Table(directory)) => {
+                    theme.directory = try_from_table(directory)?;
+                }
+                ("tag_name", TomlValue::Table(tag_name)) => {
+                    theme.tag_name = try_from_table(tag_name)?;
+                }
+                ("search_highlight", TomlValue::Table(search_highlight)) => {
+                    theme.search_highlight = try_from_table(search_highlight)?;
+                }
                 (other, _) => bail!("invalid config key `{}`", other),
             }
         }
@@ -1,10 +1,17 @@
 use crate::constants;
-use ratatui::crossterm::{ExecutableCommand, terminal};
+use ratatui::crossterm::{
+    ExecutableCommand,
+    event::{DisableBracketedPaste, DisableFocusChange, DisableMouseCapture},
+    terminal,
+};
 use std::{backtrace::Backtrace, fs, io::stdout, panic};
 
 pub fn register_backtrace_panic_handler() {
     panic::set_hook(Box::new(|info| {
         let _ = terminal::disable_raw_mode();
+        let _ = stdout().execute(DisableMouseCapture);
+        let _ = stdout().execute(DisableFocusChange);
+        let _ = stdout().execute(DisableBracketedPaste);
         let _ = stdout().execute(terminal::LeaveAlternateScreen);
         eprintln!("amusing crashed");
         if let Some(path) = dirs::cache_dir().map(|p| p.join(constants::DEFAULT_BACKTRACE_FILE)) {
@@ -1,15 +1,26 @@
-use ratatui::crossterm::event::{self, Event as TermEvent};
+use ratatui::{
+    crossterm::event::{self, Event as TermEvent, KeyCode, MouseButton, MouseEventKind},
+    widgets::TableState,
+};
 use ratatui_image::thread::ResizeResponse;
+use std::sync::Arc;
 use tui_input::backend::crossterm::EventHandler;
 
 use crate::{
-    app::{App, AppState, Screen},
+    app::{App, AppState, Popup, Screen},
+    constants,
     model::{
+        command::{self, Amount, Command},
         common::{FocusedPart, Scroll},
         connection::{MusingRequest, MusingResponse},
-        keybind::{Binding, KeybindNode},
+        files::Entry,
+        help::HelpState,
+        keybind::{self, Binding, KeybindContext, KeybindNode},
+        mouse::Clickable,
         musing::MusingStateDelta,
-        search::SearchState,
+        queue,
+        search::{Search, SearchContext, SearchState},
+        song_info::{self, SongInfoState},
     },
 };
 
@@ -23,25 +34,53 @@ pub enum AppUpdate {
     Stop,
     Play,
     Seek(i64),
+    SeekTo(u64),
+    SeekToPercent(u64),
     Speed(i16),
     Volume(i8),
+    VolumeTo(u64),
+    VolumePreset(String),
+    ToggleMute,
     Scroll(i32),
     ScrollTop,
+    ScrollTo(usize),
+    ScrollToPercent(usize),
     ScrollBottom,
     FocusLeft,
     FocusRight,
+    NextView,
+    PreviousView,
+    ToggleStatsOrder,
     StartSearch,
     EndSearch,
     IdleSearch,
     UpdateSearch,
+    CycleSearchMode,
+    ToggleCaseSensitive,
+    RecallSearch(isize),
+    NextMatch,
+    PreviousMatch,
     AddToQueue,
-    RemoveFromQueue,
+    PlayNow,
+    Reveal,
+    RemoveFromQueue(usize), // this many songs, starting from the selected one
     ClearQueue,
+    ShowInfo,
+    ClosePopup,
+    CopyValue,
     ModeGapless,
     ModeRandom,
     ModeSequential,
     ModeSingle,
     MusingUpdate,
+    Click(Clickable, usize), // the n-th visible row of a list
+    ShowHelp,
+    OpenCommandLine(String), // with something already typed in
+    RunCommand(String),
+    AddPath(String),
+    SwitchView(String),
+    SavePlaylist(String),
+    RunMacro(String),
 }
 
 #[derive(Debug)]
@@ -58,17 +97,72 @@ macro_rules! enum_stringify {
     }};
 }
 
+pub fn translate_mouse_event(app: &mut App, ev: event::MouseEvent) -> Option<Message> {
+    match ev.kind {
+        MouseEventKind::ScrollUp => Some(Message::Update(AppUpdate::Scroll(-1))),
+        MouseEventKind::ScrollDown => Some(Message::Update(AppUpdate::Scroll(1))),
+        MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left)
+            if app.popup.is_none() =>
+        {
+            let (area, clickable) = app.mouse_state.at(ev.column, ev.row)?;
+            match clickable {
+                Clickable::ProgressBar => {
+                    let (_, duration) = app.musing_state.timer?;
+                    let fraction = (ev.column - area.x) as f64 / area.width as f64;
+                    let position = (fraction * duration as f64).round() as u64;
+
+                    Some(Message::Update(AppUpdate::SeekTo(position)))
+                }
+                // only the progress bar can be dragged
+                _ if matches!(ev.kind, MouseEventKind::Drag(_)) => None,
+                // the borders of the list
+                _ if ev.row == area.top() || ev.row + 1 >= area.bottom() => None,
+                _ => Some(Message::Update(AppUpdate::Click(
+                    clickable,
+                    (ev.row - area.top() - 1) as usize,
+                ))),
+            }
+        }
+        _ => None,
+    }
+}
+
+// pasted text only goes to the search box or the command line
+pub fn translate_paste(app: &mut App, text: &str) -> Option<Message> {
+    if app.command_line.is_open() {
+        app.command_line.insert(text);
+        // nothing to update, but the line has to be redrawn
+        app.dirty = true;
+        return None;
+    }
+    if !app.searching {
+        return None;
+    }
+    let search = focused_search(app)?;
+    search.insert(text);
+
+    Some(Message::Update(AppUpdate::UpdateSearch))
+}
+
+// keys typed into the search box
+fn translate_search_input(search: &mut Search, ev: event::KeyEvent) -> Option<Message> {
+    match ev.code {
+        KeyCode::Up => Some(Message::Update(AppUpdate::RecallSearch(-1))),
+        KeyCode::Down => Some(Message::Update(AppUpdate::RecallSearch(1))),
+        _ => {
+            search.input.handle_event(&TermEvent::Key(ev));
+
+            Some(Message::Update(AppUpdate::UpdateSearch))
+        }
+    }
+}
+
 fn translate_binding_queue(app: &mut App, binding: Binding) -> Option<Message> {
     let search = &mut app.queue_state.search;
     match search.state {
         SearchState::On => match binding {
             Binding::EndSearch => Some(Message::Update(AppUpdate::IdleSearch)),
-            _ => {
-                let term_ev = TermEvent::Key(*app.key_events.last().unwrap());
-                search.input.handle_event(&term_ev);
-
-                Some(Message::Update(AppUpdate::UpdateSearch))
-            }
+            _ => translate_search_input(search, *app.key_events.last().unwrap()),
         },
         _ => match binding {
             Binding::ScrollUp => Some(Message::Update(AppUpdate::Scroll(-1))),
@@ -77,7 +171,7 @@ fn translate_binding_queue(app: &mut App, binding: Binding) -> Option<Message> {
             Binding::ScrollManyDown => Some(Message::Update(AppUpdate::Scroll(5))),
             Binding::ScrollTop => Some(Message::Update(AppUpdate::ScrollTop)),
             Binding::ScrollBottom => Some(Message::Update(AppUpdate::ScrollBottom)),
-            Binding::RemoveFromQueue => Some(Message::Update(AppUpdate::RemoveFromQueue)),
+            Binding::RemoveFromQueue => Some(Message::Update(AppUpdate::RemoveFromQueue(1))),
             Binding::ClearQueue => Some(Message::Update(AppUpdate::ClearQueue)),
             Binding::Play => Some(Message::Update(AppUpdate::Play)),
             Binding::StartSearch => Some(Message::Update(AppUpdate::StartSearch)),
@@ -97,6 +191,8 @@ fn translate_binding_library_both(app: &mut App, binding: Binding) -> Option<Mes
         Binding::ScrollBottom => Some(Message::Update(AppUpdate::ScrollBottom)),
         Binding::FocusLeft => Some(Message::Update(AppUpdate::FocusLeft)),
         Binding::FocusRight => Some(Message::Update(AppUpdate::FocusRight)),
+        Binding::NextView => Some(Message::Update(AppUpdate::NextView)),
+        Binding::PreviousView => Some(Message::Update(AppUpdate::PreviousView)),
         Binding::AddToQueue => Some(Message::Update(AppUpdate::AddToQueue)),
         Binding::StartSearch => Some(Message::Update(AppUpdate::StartSearch)),
         Binding::EndSearch => Some(Message::Update(AppUpdate::EndSearch)),
@@ -109,12 +205,7 @@ fn translate_binding_library_groups(app: &mut App, binding: Binding) -> Option<M
     match search.state {
         SearchState::On => match binding {
             Binding::EndSearch => Some(Message::Update(AppUpdate::IdleSearch)),
-            _ => {
-                let term_ev = TermEvent::Key(*app.key_events.last().unwrap());
-                search.input.handle_event(&term_ev);
-
-                Some(Message::Update(AppUpdate::UpdateSearch))
-            }
+            _ => translate_search_input(search, *app.key_events.last().unwrap()),
         },
         _ => translate_binding_library_both(app, binding),
     }
@@ -125,17 +216,123 @@ fn translate_binding_library_child(app: &mut App, binding: Binding, i: usize) ->
     match search.state {
         SearchState::On => match binding {
             Binding::EndSearch => Some(Message::Update(AppUpdate::IdleSearch)),
-            _ => {
-                let term_ev = TermEvent::Key(*app.key_events.last().unwrap());
-                search.input.handle_event(&term_ev);
-
-                Some(Message::Update(AppUpdate::UpdateSearch))
-            }
+            _ => translate_search_input(search, *app.key_events.last().unwrap()),
         },
         _ => translate_binding_library_both(app, binding),
     }
 }
 
+fn translate_binding_help(app: &mut App, binding: Binding) -> Option<Message> {
+    let Some(Popup::Help(help)) = &mut app.popup else {
+        return None;
+    };
+    let search = &mut help.search;
+    match search.state {
+        SearchState::On => match binding {
+            Binding::EndSearch => Some(Message::Update(AppUpdate::IdleSearch)),
+            Binding::CycleSearchMode => Some(Message::Update(AppUpdate::CycleSearchMode)),
+            Binding::ToggleCaseSensitive => Some(Message::Update(AppUpdate::ToggleCaseSensitive)),
+            _ => translate_search_input(search, *app.key_events.last().unwrap()),
+        },
+        SearchState::Idle => match binding {
+            Binding::NextMatch => Some(Message::Update(AppUpdate::NextMatch)),
+            Binding::PreviousMatch => Some(Message::Update(AppUpdate::PreviousMatch)),
+            Binding::EndSearch => Some(Message::Update(AppUpdate::EndSearch)),
+            _ => translate_binding_help_list(app, binding),
+        },
+        SearchState::Off => match binding {
+            Binding::EndSearch => Some(Message::Update(AppUpdate::ClosePopup)),
+            _ => translate_binding_help_list(app, binding),
+        },
+    }
+}
+
+fn translate_binding_help_list(app: &mut App, binding: Binding) -> Option<Message> {
+    match binding {
+        Binding::ScrollUp => Some(Message::Update(AppUpdate::Scroll(-1))),
+        Binding::ScrollDown => Some(Message::Update(AppUpdate::Scroll(1))),
+        Binding::ScrollManyUp => Some(Message::Update(AppUpdate::Scroll(-5))),
+        Binding::ScrollManyDown => Some(Message::Update(AppUpdate::Scroll(5))),
+        Binding::ScrollTop => Some(Message::Update(AppUpdate::ScrollTop)),
+        Binding::ScrollBottom => Some(Message::Update(AppUpdate::ScrollBottom)),
+        Binding::StartSearch => Some(Message::Update(AppUpdate::StartSearch)),
+        Binding::Help => Some(Message::Update(AppUpdate::ClosePopup)),
+        Binding::Quit => translate_binding_common(app, binding),
+        _ => None,
+    }
+}
+
+fn translate_binding_popup(app: &mut App, binding: Binding) -> Option<Message> {
+    if let Some(Popup::Help(_)) = app.popup {
+        return translate_binding_help(app, binding);
+    }
+    match binding {
+        Binding::ScrollUp => Some(Message::Update(AppUpdate::Scroll(-1))),
+        Binding::ScrollDown => Some(Message::Update(AppUpdate::Scroll(1))),
+        Binding::ScrollManyUp => Some(Message::Update(AppUpdate::Scroll(-5))),
+        Binding::ScrollManyDown => Some(Message::Update(AppUpdate::Scroll(5))),
+        Binding::ScrollTop => Some(Message::Update(AppUpdate::ScrollTop)),
+        Binding::ScrollBottom => Some(Message::Update(AppUpdate::ScrollBottom)),
+        Binding::Copy => Some(Message::Update(AppUpdate::CopyValue)),
+        Binding::Info | Binding::EndSearch => Some(Message::Update(AppUpdate::ClosePopup)),
+        Binding::Quit => translate_binding_common(app, binding),
+        _ => None,
+    }
+}
+
+fn translate_binding_files(app: &mut App, binding: Binding) -> Option<Message> {
+    let search = &mut app.files_state.search;
+    match search.state {
+        SearchState::On => match binding {
+            Binding::EndSearch => Some(Message::Update(AppUpdate::IdleSearch)),
+            _ => translate_search_input(search, *app.key_events.last().unwrap()),
+        },
+        _ => match binding {
+            Binding::ScrollUp => Some(Message::Update(AppUpdate::Scroll(-1))),
+            Binding::ScrollDown => Some(Message::Update(AppUpdate::Scroll(1))),
+            Binding::ScrollManyUp => Some(Message::Update(AppUpdate::Scroll(-5))),
+            Binding::ScrollManyDown => Some(Message::Update(AppUpdate::Scroll(5))),
+            Binding::ScrollTop => Some(Message::Update(AppUpdate::ScrollTop)),
+            Binding::ScrollBottom => Some(Message::Update(AppUpdate::ScrollBottom)),
+            Binding::FocusLeft => Some(Message::Update(AppUpdate::FocusLeft)),
+            Binding::FocusRight => Some(Message::Update(AppUpdate::FocusRight)),
+            // plays the selected song, directories are entered instead
+            Binding::Play => match app.files_state.selected_file() {
+                Some(_) => Some(Message::Update(AppUpdate::PlayNow)),
+                None => Some(Message::Update(AppUpdate::FocusRight)),
+            },
+            Binding::AddToQueue => Some(Message::Update(AppUpdate::AddToQueue)),
+            Binding::StartSearch => Some(Message::Update(AppUpdate::StartSearch)),
+            Binding::EndSearch => Some(Message::Update(AppUpdate::EndSearch)),
+            _ => translate_binding_common(app, binding),
+        },
+    }
+}
+
+fn translate_binding_global_search(app: &mut App, binding: Binding) -> Option<Message> {
+    let search = &mut app.global_search_state.search;
+    match search.state {
+        SearchState::On => match binding {
+            Binding::EndSearch => Some(Message::Update(AppUpdate::IdleSearch)),
+            _ => translate_search_input(search, *app.key_events.last().unwrap()),
+        },
+        _ => match binding {
+            Binding::ScrollUp => Some(Message::Update(AppUpdate::Scroll(-1))),
+            Binding::ScrollDown => Some(Message::Update(AppUpdate::Scroll(1))),
+            Binding::ScrollManyUp => Some(Message::Update(AppUpdate::Scroll(-5))),
+            Binding::ScrollManyDown => Some(Message::Update(AppUpdate::Scroll(5))),
+            Binding::ScrollTop => Some(Message::Update(AppUpdate::ScrollTop)),
+            Binding::ScrollBottom => Some(Message::Update(AppUpdate::ScrollBottom)),
+            Binding::AddToQueue => Some(Message::Update(AppUpdate::AddToQueue)),
+            Binding::Play => Some(Message::Update(AppUpdate::PlayNow)),
+            Binding::Reveal | Binding::FocusRight => Some(Message::Update(AppUpdate::Reveal)),
+            Binding::StartSearch => Some(Message::Update(AppUpdate::StartSearch)),
+            Binding::EndSearch => Some(Message::Update(AppUpdate::EndSearch)),
+            _ => translate_binding_common(app, binding),
+        },
+    }
+}
+
 pub fn translate_binding_common(app: &mut App, binding: Binding) -> Option<Message> {
     match binding {
         Binding::Quit => Some(Message::SwitchAppState(AppState::Done)),
@@ -151,43 +348,263 @@ pub fn translate_binding_common(app: &mut App, binding: Binding) -> Option<Messa
         Binding::SpeedDown => Some(Message::Update(AppUpdate::Speed(-app.config.speed_step))),
         Binding::VolumeUp => Some(Message::Update(AppUpdate::Volume(app.config.volume_step))),
         Binding::VolumeDown => Some(Message::Update(AppUpdate::Volume(-app.config.volume_step))),
+        Binding::Mute => Some(Message::Update(AppUpdate::ToggleMute)),
         Binding::ModeSequential => Some(Message::Update(AppUpdate::ModeSequential)),
         Binding::ModeSingle => Some(Message::Update(AppUpdate::ModeSingle)),
         Binding::ModeRandom => Some(Message::Update(AppUpdate::ModeRandom)),
         Binding::ModeGapless => Some(Message::Update(AppUpdate::ModeGapless)),
         Binding::MusingUpdate => Some(Message::Update(AppUpdate::MusingUpdate)),
+        Binding::Info => Some(Message::Update(AppUpdate::ShowInfo)),
         Binding::ScreenCover => Some(Message::SwitchScreen(Screen::Cover)),
         Binding::ScreenQueue => Some(Message::SwitchScreen(Screen::Queue)),
         Binding::ScreenLibrary => Some(Message::SwitchScreen(Screen::Library)),
+        Binding::ScreenFiles => Some(Message::SwitchScreen(Screen::Files)),
+        Binding::ScreenStats => Some(Message::SwitchScreen(Screen::Stats)),
+        Binding::ScreenSearch => Some(Message::SwitchScreen(Screen::Search)),
+        Binding::SeekTo => Some(Message::Update(AppUpdate::OpenCommandLine("seek ".into()))),
+        Binding::Restart => Some(Message::Update(AppUpdate::SeekTo(0))),
+        Binding::CommandLine => Some(Message::Update(AppUpdate::OpenCommandLine(String::new()))),
+        Binding::Help => Some(Message::Update(AppUpdate::ShowHelp)),
+        Binding::Command(line) => Some(Message::Update(AppUpdate::RunCommand(line))),
+        Binding::Macro(name) => Some(Message::Update(AppUpdate::RunMacro(name))),
         _ => None,
     }
 }
 
+// keys typed into the command line
+fn translate_command_line_input(app: &mut App, ev: event::KeyEvent) -> Option<Message> {
+    let command_line = &mut app.command_line;
+    match ev.code {
+        KeyCode::Esc => {
+            command_line.close();
+            None
+        }
+        KeyCode::Backspace if command_line.value().is_empty() => {
+            command_line.close();
+            None
+        }
+        KeyCode::Enter => {
+            let line = command_line.close()?;
+            command_line.history.push(&line);
+
+            Some(Message::Update(AppUpdate::RunCommand(line)))
+        }
+        KeyCode::Tab | KeyCode::BackTab => {
+            let delta = if ev.code == KeyCode::Tab { 1 } else { -1 };
+            let views = &app.library_state.views;
+            let files = &app.files_state;
+            let presets = &app.config.volume_presets;
+            command_line.complete(delta, |name, arg| match name {
+                "add" => files.completions(arg),
+                "mode" => command::MODES.iter().map(|mode| mode.to_string()).collect(),
+                "view" => views.iter().map(|view| view.name.clone()).collect(),
+                "volume" => presets.keys().cloned().collect(),
+                _ => Vec::new(),
+            });
+            None
+        }
+        KeyCode::Up => {
+            command_line.recall(-1);
+            None
+        }
+        KeyCode::Down => {
+            command_line.recall(1);
+            None
+        }
+        _ => {
+            if let Some(input) = command_line.input.as_mut() {
+                input.handle_event(&TermEvent::Key(ev));
+            }
+            command_line.edited();
+            None
+        }
+    }
+}
+
+fn translate_command(app: &mut App, command: Command) -> Option<Message> {
+    let update = match command {
+        // the queue can be cleared from any screen (e.g. by a macro run in the library)
+        Command::Binding(Binding::ClearQueue) => AppUpdate::ClearQueue,
+        Command::Binding(binding) => {
+            let msg = translate_binding(app, binding.clone());
+            if msg.is_none() {
+                app.status_msg = Some(format!("`{}` can't be used on this screen", binding));
+            }
+            return msg;
+        }
+        Command::Add(path) => AppUpdate::AddPath(path),
+        Command::SavePlaylist(name) => AppUpdate::SavePlaylist(name),
+        Command::Seek(Amount::To(position)) => AppUpdate::SeekTo(position),
+        Command::Seek(Amount::By(delta)) => AppUpdate::Seek(delta),
+        Command::SeekPercent(percent) => AppUpdate::SeekToPercent(percent),
+        // musing only changes the speed by a delta
+        Command::Speed(amount) => {
+            let delta = match amount {
+                Amount::To(speed) => {
+                    i64::try_from(speed).unwrap_or(i64::MAX) - app.musing_state.speed as i64
+                }
+                Amount::By(delta) => delta,
+            };
+            AppUpdate::Speed(delta.clamp(i16::MIN.into(), i16::MAX.into()) as i16)
+        }
+        Command::Volume(Amount::To(volume)) => AppUpdate::VolumeTo(volume),
+        Command::Volume(Amount::By(delta)) => {
+            AppUpdate::Volume(delta.clamp(i8::MIN.into(), i8::MAX.into()) as i8)
+        }
+        Command::VolumePreset(name) => AppUpdate::VolumePreset(name),
+        Command::View(name) => AppUpdate::SwitchView(name),
+    };
+
+    Some(Message::Update(update))
+}
+
+// what a binding does in the current screen
+fn translate_binding(app: &mut App, binding: Binding) -> Option<Message> {
+    match app.screen {
+        _ if app.popup.is_some() => translate_binding_popup(app, binding),
+        Screen::Queue => translate_binding_queue(app, binding),
+        Screen::Library => match app.library_state.focused_part {
+            FocusedPart::Groups => translate_binding_library_groups(app, binding),
+            FocusedPart::Child(i) => translate_binding_library_child(app, binding, i),
+        },
+        Screen::Files => translate_binding_files(app, binding),
+        Screen::Search => translate_binding_global_search(app, binding),
+        Screen::Stats => match binding {
+            Binding::NextView | Binding::PreviousView => {
+                Some(Message::Update(AppUpdate::ToggleStatsOrder))
+            }
+            _ => translate_binding_common(app, binding),
+        },
+        _ => translate_binding_common(app, binding),
+    }
+}
+
+// digits typed before a binding, vim-style (e.g. 10j)
+// a count can start with any unbound digit other than 0, after that every digit extends it
+fn translate_count(app: &mut App, ev: event::KeyEvent) -> bool {
+    let KeyCode::Char(c) = ev.code else {
+        return false;
+    };
+    let Some(digit) = c.to_digit(10) else {
+        return false;
+    };
+    if !ev.modifiers.is_empty()
+        || app.searching
+        || app.popup.is_some()
+        || !app.key_events.is_empty()
+    {
+        return false;
+    }
+    if app.count.is_none()
+        && (digit == 0
+            || app
+                .config
+                .keybind
+                .translate(keybind_context(app), &[ev])
+                .is_some())
+    {
+        return false;
+    }
+    let count = app.count.unwrap_or_default() * 10 + digit as usize;
+    app.count = Some(count.min(constants::MAX_COUNT));
+
+    true
+}
+
+// repeats or scales the update by the count typed before its binding
+fn with_count(msg: Message, count: Option<usize>) -> Message {
+    let Some(n) = count else {
+        return msg;
+    };
+    let update = match msg {
+        Message::Update(update) => update,
+        other => return other,
+    };
+    let n_i64 = n as i64;
+    Message::Update(match update {
+        AppUpdate::Scroll(delta) => AppUpdate::Scroll(delta.saturating_mul(n as i32)),
+        AppUpdate::Seek(seconds) => AppUpdate::Seek(seconds.saturating_mul(n_i64)),
+        AppUpdate::Volume(delta) => {
+            AppUpdate::Volume((delta as i64 * n_i64).clamp(i8::MIN.into(), i8::MAX.into()) as i8)
+        }
+        AppUpdate::Speed(delta) => {
+            AppUpdate::Speed((delta as i64 * n_i64).clamp(i16::MIN.into(), i16::MAX.into()) as i16)
+        }
+        AppUpdate::RemoveFromQueue(_) => AppUpdate::RemoveFromQueue(n),
+        // like 5G in vim, go to the 5th row
+        AppUpdate::ScrollTop | AppUpdate::ScrollBottom => AppUpdate::ScrollTo(n - 1),
+        other => other,
+    })
+}
+
 pub fn translate_key_event(app: &mut App, ev: event::KeyEvent) -> Option<Message> {
+    if app.command_line.is_open() {
+        return translate_command_line_input(app, ev);
+    }
+    if translate_count(app, ev) {
+        return None;
+    }
     app.key_events.push(ev);
+    let idle = search_is_idle(app);
     let default_translation = KeybindNode::Terminal(Binding::Other);
-    let mut translation = app
+    let translation = app
         .config
         .keybind
-        .translate(&app.key_events)
-        .unwrap_or(&default_translation);
+        .translate(keybind_context(app), &app.key_events);
+    // a single unbound key is just ignored, but a sequence was probably meant as something
+    if translation.is_none() && app.key_events.len() > 1 && !app.searching {
+        app.status_msg = Some(format!(
+            "`{}` isn't bound to anything",
+            keybind::key_sequence_to_string(&app.key_events)
+        ));
+    }
+    let mut translation = translation.unwrap_or(&default_translation);
     // ignore normal keybinds if we're searching
-    if app.searching && !matches!(translation, KeybindNode::Terminal(Binding::EndSearch)) {
+    if app.searching
+        && !matches!(
+            translation,
+            KeybindNode::Terminal(
+                Binding::EndSearch | Binding::CycleSearchMode | Binding::ToggleCaseSensitive
+            )
+        )
+    {
         translation = &KeybindNode::Terminal(Binding::Other);
     }
+    // jumping between matches needs an idle search, otherwise the keys do what they do globally
+    // (N is `previous_match` in the screens with lists, but `next` everywhere else)
+    if !idle
+        && let KeybindNode::Terminal(Binding::NextMatch | Binding::PreviousMatch) = translation
+        && let Some(global @ KeybindNode::Terminal(_)) =
+            app.config.keybind.global.translate(&app.key_events)
+    {
+        translation = global;
+    }
     match translation {
         KeybindNode::Terminal(binding) => {
+            let count = app.count.take();
             let res = match app.screen {
-                Screen::Queue => translate_binding_queue(app, *binding),
-                Screen::Library => match app.library_state.focused_part {
-                    FocusedPart::Groups => translate_binding_library_groups(app, *binding),
-                    FocusedPart::Child(i) => translate_binding_library_child(app, *binding, i),
-                },
-                _ => translate_binding_common(app, *binding),
+                _ if app.popup.is_some() => translate_binding_popup(app, binding.clone()),
+                // only makes sense with a count (50% is the middle of the list)
+                _ if matches!(binding, Binding::ScrollPercent) => {
+                    count.map(|n| Message::Update(AppUpdate::ScrollToPercent(n.min(100))))
+                }
+                _ if app.searching && matches!(binding, Binding::CycleSearchMode) => {
+                    Some(Message::Update(AppUpdate::CycleSearchMode))
+                }
+                _ if app.searching && matches!(binding, Binding::ToggleCaseSensitive) => {
+                    Some(Message::Update(AppUpdate::ToggleCaseSensitive))
+                }
+                _ if idle && matches!(binding, Binding::NextMatch | Binding::PreviousMatch) => {
+                    Some(Message::Update(match binding {
+                        Binding::NextMatch => AppUpdate::NextMatch,
+                        _ => AppUpdate::PreviousMatch,
+                    }))
+                }
+                _ => translate_binding(app, binding.clone()),
             };
             app.key_events.clear();
 
-            res
+            res.map(|msg| with_count(msg, count))
         }
         KeybindNode::Transition(_) => None,
     }
@@ -197,41 +614,183 @@ pub fn translate_key_event(app: &mut App, ev: event::KeyEvent) -> Option<Message
 // a response to this request will arrive later at some point
 pub fn update_on_message(app: &mut App, msg: Message) {
     match msg {
-        Message::SwitchScreen(screen) => app.screen = screen,
+        Message::SwitchScreen(screen) => {
+            app.screen = screen;
+            // the search screen is only useful when searching, so start right away
+            if let Screen::Search = app.screen
+                && let SearchState::Off = app.global_search_state.search.state
+            {
+                update_on_message(app, Message::Update(AppUpdate::StartSearch));
+            }
+        }
         Message::SwitchAppState(app_state) => app.app_state = app_state,
         Message::Update(update) => match update {
             AppUpdate::MusingUpdate => update_library(app),
-            AppUpdate::Scroll(delta) => match app.screen {
-                Screen::Queue => app.queue_state.scroll(delta),
-                Screen::Library => app.library_state.scroll(delta),
+            AppUpdate::Scroll(delta) => match (&mut app.popup, &app.screen) {
+                (Some(Popup::SongInfo(info)), _) => info.scroll(delta),
+                (Some(Popup::Help(help)), _) => help.scroll(delta),
+                (None, Screen::Queue) => app.queue_state.scroll(delta),
+                (None, Screen::Library) => app.library_state.scroll(delta),
+                (None, Screen::Files) => app.files_state.scroll(delta),
+                (None, Screen::Search) => app.global_search_state.scroll(delta),
                 _ => (),
             },
-            AppUpdate::ScrollTop => match app.screen {
-                Screen::Queue => app.queue_state.scroll_to_top(),
-                Screen::Library => app.library_state.scroll_to_top(),
+            AppUpdate::ScrollTop => match (&mut app.popup, &app.screen) {
+                (Some(Popup::SongInfo(info)), _) => info.scroll_to_top(),
+                (Some(Popup::Help(help)), _) => help.scroll_to_top(),
+                (None, Screen::Queue) => app.queue_state.scroll_to_top(),
+                (None, Screen::Library) => app.library_state.scroll_to_top(),
+                (None, Screen::Files) => app.files_state.scroll_to_top(),
+                (None, Screen::Search) => app.global_search_state.scroll_to_top(),
                 _ => (),
             },
-            AppUpdate::ScrollBottom => match app.screen {
-                Screen::Queue => app.queue_state.scroll_to_bottom(),
-                Screen::Library => app.library_state.scroll_to_bottom(),
+            AppUpdate::ScrollBottom => match (&mut app.popup, &app.screen) {
+                (Some(Popup::SongInfo(info)), _) => info.scroll_to_bottom(),
+                (Some(Popup::Help(help)), _) => help.scroll_to_bottom(),
+                (None, Screen::Queue) => app.queue_state.scroll_to_bottom(),
+                (None, Screen::Library) => app.library_state.scroll_to_bottom(),
+                (None, Screen::Files) => app.files_state.scroll_to_bottom(),
+                (None, Screen::Search) => app.global_search_state.scroll_to_bottom(),
                 _ => (),
             },
-            AppUpdate::StartSearch => match app.screen {
-                Screen::Queue => {
-                    app.queue_state.search_on();
-                    app.searching = true;
+            AppUpdate::ScrollTo(i) => scroll_to(app, i),
+            AppUpdate::ScrollToPercent(percent) => {
+                let n_rows = focused_n_rows(app);
+                scroll_to(app, n_rows.saturating_sub(1) * percent / 100);
+            }
+            AppUpdate::OpenCommandLine(value) => {
+                app.command_line.open();
+                app.command_line.set_value(value);
+            }
+            AppUpdate::RunCommand(line) => match line.parse::<Command>() {
+                Ok(command) => {
+                    if let Some(msg) = translate_command(app, command) {
+                        update_on_message(app, msg);
+                    }
                 }
-                Screen::Library => {
-                    match app.library_state.focused_part {
-                        FocusedPart::Groups => app.library_state.search_on(),
-                        FocusedPart::Child(i) => {
-                            app.library_state.children[i].search_on();
+                Err(e) => app.status_msg = Some(format!("{}", e)),
+            },
+            AppUpdate::RunMacro(name) => match app.config.macros.get(&name) {
+                Some(steps) => {
+                    app.macro_state.start(&name, steps);
+                    run_macro(app);
+                }
+                None => app.status_msg = Some(format!("unknown macro `{}`", name)),
+            },
+            AppUpdate::AddPath(path) => {
+                let songs = app.files_state.songs_at(&path);
+                if songs.is_empty() {
+                    app.status_msg = Some(format!("no songs found at `{}`", path));
+                } else {
+                    app.connection.send(MusingRequest::AddToQueue(songs));
+                }
+            }
+            AppUpdate::SwitchView(name) => {
+                let library = &mut app.library_state;
+                match library.views.iter().position(|view| view.name == name) {
+                    Some(i) => {
+                        if library.switch_view(i) {
+                            request_library_view(app);
                         }
-                    };
-                    app.searching = true
+                        app.screen = Screen::Library;
+                    }
+                    None => app.status_msg = Some(format!("no library view named `{}`", name)),
                 }
-                _ => (),
+            }
+            AppUpdate::SavePlaylist(name) => {
+                app.status_msg = Some(match queue::save_playlist(&name, &app.musing_state.queue) {
+                    Ok(path) => format!("saved the queue to {}", path.display()),
+                    Err(e) => format!("could not save the playlist ({})", e),
+                });
+            }
+            AppUpdate::ShowInfo => match selected_song(app) {
+                Some(path) => app.connection.send(MusingRequest::SongInfo(path)),
+                None => app.status_msg = Some("no song selected".into()),
             },
+            AppUpdate::ShowHelp => {
+                app.popup = Some(Popup::Help(HelpState::new(&app.config.keybind)));
+            }
+            AppUpdate::ClosePopup => app.popup = None,
+            AppUpdate::CopyValue => {
+                if let Some(Popup::SongInfo(info)) = &app.popup
+                    && let Some((tag, value)) = info.selected_row()
+                {
+                    app.status_msg = Some(match song_info::copy_to_clipboard(value) {
+                        Ok(_) => format!("copied `{}` to the clipboard", tag),
+                        Err(e) => format!("could not copy ({})", e),
+                    });
+                }
+            }
+            AppUpdate::StartSearch => {
+                let (filter, mode) = (app.config.search_filter, app.config.search_mode);
+                let case_sensitive = app.config.search_case_sensitive;
+                if let Some(context) = search_context(app) {
+                    app.search_history.entry(context).or_default().reset();
+                }
+                if let Some(search) = focused_search(app) {
+                    if let SearchState::Idle = search.state {
+                        search.resume();
+                        app.searching = true;
+                        return;
+                    }
+                    search.filter = filter;
+                    search.mode = mode;
+                    search.case_sensitive = case_sensitive;
+                }
+                if let Some(Popup::Help(help)) = &mut app.popup {
+                    help.search_on();
+                    app.searching = true;
+                    return;
+                }
+                match app.screen {
+                    Screen::Queue => {
+                        app.queue_state.search_on();
+                        app.searching = true;
+                    }
+                    Screen::Library => {
+                        match app.library_state.focused_part {
+                            FocusedPart::Groups => app.library_state.search_on(),
+                            FocusedPart::Child(i) => {
+                                app.library_state.children[i].search_on();
+                            }
+                        };
+                        app.searching = true
+                    }
+                    Screen::Files => {
+                        app.files_state.search_on();
+                        app.searching = true;
+                    }
+                    Screen::Search => {
+                        app.global_search_state.search_on();
+                        app.searching = true;
+                    }
+                    _ => (),
+                }
+            }
+            // the help has its own search, on top of the screen
+            AppUpdate::EndSearch | AppUpdate::IdleSearch | AppUpdate::UpdateSearch
+                if matches!(app.popup, Some(Popup::Help(_))) =>
+            {
+                let context = search_context(app);
+                let Some(Popup::Help(help)) = &mut app.popup else {
+                    return;
+                };
+                let search = &mut help.search;
+                match update {
+                    AppUpdate::EndSearch => search.off(),
+                    AppUpdate::IdleSearch => {
+                        if let Some(context) = context {
+                            app.search_history
+                                .entry(context)
+                                .or_default()
+                                .push(search.input.value());
+                        }
+                        search.idle();
+                    }
+                    _ => search.pattern_update(search.input.value().to_string()),
+                }
+                app.searching = matches!(search.state, SearchState::On);
+            }
             AppUpdate::EndSearch => match app.screen {
                 Screen::Queue => {
                     app.queue_state.search.off();
@@ -246,24 +805,51 @@ pub fn update_on_message(app: &mut App, msg: Message) {
                     };
                     app.searching = false
                 }
-                _ => (),
-            },
-            AppUpdate::IdleSearch => match app.screen {
-                Screen::Queue => {
-                    app.queue_state.search.idle();
+                Screen::Files => {
+                    app.files_state.search.off();
                     app.searching = false;
                 }
-                Screen::Library => {
-                    match app.library_state.focused_part {
-                        FocusedPart::Groups => app.library_state.search.idle(),
-                        FocusedPart::Child(i) => {
-                            app.library_state.children[i].search.idle();
-                        }
-                    };
+                Screen::Search => {
+                    app.global_search_state.search.off();
                     app.searching = false;
                 }
                 _ => (),
             },
+            AppUpdate::IdleSearch => {
+                let pattern = focused_search(app).map(|search| search.input.value().to_string());
+                if let Some(context) = search_context(app)
+                    && let Some(pattern) = pattern
+                {
+                    app.search_history
+                        .entry(context)
+                        .or_default()
+                        .push(&pattern);
+                }
+                match app.screen {
+                    Screen::Queue => {
+                        app.queue_state.search.idle();
+                        app.searching = false;
+                    }
+                    Screen::Library => {
+                        match app.library_state.focused_part {
+                            FocusedPart::Groups => app.library_state.search.idle(),
+                            FocusedPart::Child(i) => {
+                                app.library_state.children[i].search.idle();
+                            }
+                        };
+                        app.searching = false;
+                    }
+                    Screen::Files => {
+                        app.files_state.search.idle();
+                        app.searching = false;
+                    }
+                    Screen::Search => {
+                        app.global_search_state.search.idle();
+                        app.searching = false;
+                    }
+                    _ => (),
+                }
+            }
             AppUpdate::UpdateSearch => match app.screen {
                 Screen::Queue => {
                     let pattern = app.queue_state.search.input.value().to_string();
@@ -280,40 +866,188 @@ pub fn update_on_message(app: &mut App, msg: Message) {
                         app.library_state.children[i].search.pattern_update(pattern);
                     }
                 },
+                Screen::Files => {
+                    let pattern = app.files_state.search.input.value().to_string();
+                    app.files_state.search.pattern_update(pattern);
+                }
+                Screen::Search => {
+                    let search = &mut app.global_search_state.search;
+                    let pattern = search.input.value().to_string();
+                    search.pattern_update(pattern);
+                }
                 _ => (),
             },
-            AppUpdate::FocusLeft => {
-                if let Screen::Library = app.screen {
-                    app.library_state.focus_left();
+            AppUpdate::CycleSearchMode => {
+                if let Some(search) = focused_search(app) {
+                    search.cycle_mode();
                 }
             }
-            AppUpdate::FocusRight => {
-                if let Screen::Library = app.screen {
-                    app.library_state.focus_right();
+            AppUpdate::ToggleCaseSensitive => {
+                if let Some(search) = focused_search(app) {
+                    search.toggle_case_sensitive();
                 }
             }
-            AppUpdate::AddToQueue => {
-                if let Some(songs) = app.library_state.selected_songs() {
+            AppUpdate::RecallSearch(delta) => {
+                if let Some(context) = search_context(app)
+                    && let Some(search) = focused_search(app)
+                {
+                    let current = search.input.value().to_string();
+                    let history = app.search_history.entry(context).or_default();
+                    let pattern = history.recall(delta, &current);
+                    if let Some(search) = focused_search(app) {
+                        search.set_pattern(pattern);
+                    }
+                }
+            }
+            AppUpdate::NextMatch | AppUpdate::PreviousMatch => {
+                let delta = if let AppUpdate::NextMatch = update {
+                    1
+                } else {
+                    -1
+                };
+                if let Some((state, search)) = focused_list(app)
+                    && let Some(i) = search.next_match(state.selected(), delta)
+                {
+                    state.select(Some(i));
+                }
+            }
+            AppUpdate::FocusLeft => match app.screen {
+                Screen::Library => app.library_state.focus_left(),
+                Screen::Files => app.files_state.leave(),
+                _ => (),
+            },
+            AppUpdate::FocusRight => match app.screen {
+                Screen::Library => app.library_state.focus_right(),
+                Screen::Files => app.files_state.enter(),
+                _ => (),
+            },
+            AppUpdate::NextView => {
+                if app.library_state.cycle_view(1) {
+                    request_library_view(app);
+                }
+            }
+            AppUpdate::PreviousView => {
+                if app.library_state.cycle_view(-1) {
+                    request_library_view(app);
+                }
+            }
+            AppUpdate::ToggleStatsOrder => app.stats_state.toggle_order(),
+            AppUpdate::AddToQueue => match app.screen {
+                Screen::Library => {
+                    if let Some(songs) = app.library_state.selected_songs() {
+                        app.connection
+                            .send(MusingRequest::AddToQueue(songs.to_vec()));
+                        app.library_state.scroll(1);
+                    }
+                }
+                Screen::Files => {
+                    let songs = app.files_state.selected_songs();
+                    if !songs.is_empty() {
+                        app.connection.send(MusingRequest::AddToQueue(songs));
+                        app.files_state.scroll(1);
+                    }
+                }
+                Screen::Search => {
+                    if let Some(song) = app.global_search_state.selected_song() {
+                        app.connection
+                            .send(MusingRequest::AddToQueue(vec![song.to_string()]));
+                        app.global_search_state.scroll(1);
+                    }
+                }
+                _ => (),
+            },
+            AppUpdate::Click(clickable, row) => click(app, clickable, row),
+            AppUpdate::PlayNow => {
+                let song = match app.screen {
+                    Screen::Files => app.files_state.selected_file(),
+                    Screen::Search => app.global_search_state.selected_song(),
+                    _ => None,
+                };
+                if let Some(song) = song {
+                    // the song gets played once musing is done adding it (see `MusingResponse::Synced`)
+                    app.play_when_queued = Some(song.to_string());
                     app.connection
-                        .send(MusingRequest::AddToQueue(songs.to_vec()));
-                    app.library_state.scroll(1);
+                        .send(MusingRequest::AddToQueue(vec![song.to_string()]));
+                    app.connection.send(MusingRequest::Sync);
+                }
+            }
+            AppUpdate::Reveal => {
+                if let Some(song) = app.global_search_state.selected_song() {
+                    let song = song.to_string();
+                    if app.library_state.reveal(&song) {
+                        app.screen = Screen::Library;
+                    } else {
+                        app.status_msg = Some("song not found in the current view".into());
+                    }
                 }
             }
             AppUpdate::Play => {
-                if let Some(i) = app.queue_state.unordered_selected() {
-                    app.connection
-                        .send(MusingRequest::Play(app.musing_state.queue[i].id));
+                if let Some(song) = app
+                    .queue_state
+                    .unordered_selected()
+                    .and_then(|i| app.musing_state.queue.get(i))
+                {
+                    app.connection.send(MusingRequest::Play(song.id));
                 }
             }
-            AppUpdate::RemoveFromQueue => {
-                if let Some(i) = app.queue_state.unordered_selected() {
-                    app.connection
-                        .send(MusingRequest::Remove(app.musing_state.queue[i].id));
+            AppUpdate::RemoveFromQueue(n) => {
+                if let Some(i) = app.queue_state.state.selected() {
+                    let queue = &app.queue_state;
+                    let end = (i + n).min(queue.search.n_rows(queue.group.len()));
+                    // the order of a search might be from before the queue shrank
+                    for song in queue
+                        .search
+                        .real_range(i..end)
+                        .into_iter()
+                        .filter_map(|j| app.musing_state.queue.get(j))
+                    {
+                        app.connection.send(MusingRequest::Remove(song.id));
+                    }
                 }
             }
             AppUpdate::Seek(seconds) => app.connection.send(MusingRequest::Seek(seconds)),
+            // musing only seeks relative to the current position
+            AppUpdate::SeekTo(position) => {
+                if let Some((elapsed, duration)) = app.musing_state.timer
+                    && !app.musing_state.is_stopped()
+                {
+                    let position = position.min(duration);
+                    app.connection
+                        .send(MusingRequest::Seek(position as i64 - elapsed as i64));
+                    // so that the next seek (e.g. while dragging) starts from here
+                    app.musing_state.timer = Some((position, duration));
+                }
+            }
+            AppUpdate::SeekToPercent(percent) => {
+                if let Some((_, duration)) = app.musing_state.timer {
+                    let position = duration * percent / 100;
+                    update_on_message(app, Message::Update(AppUpdate::SeekTo(position)));
+                }
+            }
             AppUpdate::Speed(delta) => app.connection.send(MusingRequest::Speed(delta)),
-            AppUpdate::Volume(delta) => app.connection.send(MusingRequest::Volume(delta)),
+            AppUpdate::Volume(delta) => {
+                app.unmuted_volume = None;
+                app.connection.send(MusingRequest::Volume(delta));
+            }
+            AppUpdate::VolumeTo(volume) => {
+                app.unmuted_volume = None;
+                set_volume(app, volume);
+            }
+            AppUpdate::VolumePreset(name) => match app.config.volume_presets.get(&name) {
+                Some(&volume) => {
+                    app.unmuted_volume = None;
+                    set_volume(app, volume);
+                }
+                None => app.status_msg = Some(format!("no volume preset named `{}`", name)),
+            },
+            AppUpdate::ToggleMute => match app.unmuted_volume.take() {
+                Some(volume) => set_volume(app, volume),
+                None if app.musing_state.volume > 0 => {
+                    app.unmuted_volume = Some(app.musing_state.volume);
+                    set_volume(app, 0);
+                }
+                None => (),
+            },
             other => app
                 .connection
                 .send(MusingRequest::Other(enum_stringify!(other))),
@@ -321,38 +1055,328 @@ pub fn update_on_message(app: &mut App, msg: Message) {
     };
 }
 
+// musing only changes the volume by a delta
+fn set_volume(app: &mut App, volume: u64) {
+    let volume = volume.min(100);
+    let delta = volume as i64 - app.musing_state.volume as i64;
+    app.connection.send(MusingRequest::Volume(delta as i8));
+    // so that toggling mute twice in a row doesn't have to wait for musing
+    app.musing_state.volume = volume;
+}
+
+// runs the steps of the current macro until one of them has to wait for musing,
+// the rest are run once it's done (see `MusingResponse::Synced`)
+fn run_macro(app: &mut App) {
+    loop {
+        // the steps are meant for the screen, not for a search or a popup opened in the meantime
+        if app.macro_state.is_running() && (app.searching || app.popup.is_some()) {
+            stop_macro(app, "interrupted");
+            return;
+        }
+        let Some(command) = app.macro_state.next_step() else {
+            return;
+        };
+        let n_sent = app.connection.n_sent();
+        match translate_command(app, command) {
+            Some(msg) => update_on_message(app, msg),
+            // the status message says why
+            None => {
+                let reason = app.status_msg.take().unwrap_or_default();
+                stop_macro(app, &reason);
+                return;
+            }
+        }
+        // the pattern of a search has to be typed by hand, so that's where the macro ends
+        if app.searching {
+            app.macro_state.stop();
+            return;
+        }
+        // e.g. playing the first song of the queue only makes sense once it's been cleared
+        if app.connection.n_sent() > n_sent && app.macro_state.is_running() {
+            app.macro_state.wait();
+            app.connection.send(MusingRequest::Sync);
+        }
+    }
+}
+
+fn stop_macro(app: &mut App, reason: &str) {
+    app.status_msg = Some(match app.macro_state.stop() {
+        Some(name) => format!("{} (macro `{}` stopped)", reason, name),
+        None => reason.to_string(),
+    });
+}
+
+// selects the clicked row (focusing its list first), a double click acts like `play`
+fn click(app: &mut App, clickable: Clickable, row: usize) {
+    if app.searching {
+        update_on_message(app, Message::Update(AppUpdate::IdleSearch));
+    }
+    match (clickable, &app.library_state.focused_part) {
+        (Clickable::LibraryGroups, FocusedPart::Child(_)) => app.library_state.focus_left(),
+        (Clickable::LibrarySongs, FocusedPart::Groups) => app.library_state.focus_right(),
+        _ => (),
+    }
+    let n_rows = focused_n_rows(app);
+    let Some((state, _)) = focused_list(app) else {
+        return;
+    };
+    let i = state.offset() + row;
+    if i >= n_rows {
+        return;
+    }
+    state.select(Some(i));
+    if !app.mouse_state.click(clickable, i) {
+        return;
+    }
+    let update = match clickable {
+        Clickable::Queue => AppUpdate::Play,
+        Clickable::LibraryGroups | Clickable::LibrarySongs => AppUpdate::AddToQueue,
+        Clickable::Files => match app
+            .files_state
+            .unordered_selected()
+            .and_then(|i| app.files_state.entry(i))
+        {
+            Some(Entry::Directory(_)) => AppUpdate::FocusRight,
+            _ => AppUpdate::PlayNow,
+        },
+        Clickable::Search => AppUpdate::PlayNow,
+        Clickable::ProgressBar => return,
+    };
+    update_on_message(app, Message::Update(update));
+}
+
+fn scroll_to(app: &mut App, i: usize) {
+    let n_rows = focused_n_rows(app);
+    if n_rows == 0 {
+        return;
+    }
+    if let Some((state, _)) = focused_list(app) {
+        state.select(Some(i.min(n_rows - 1)));
+    }
+}
+
+// how many rows the list that the user is looking at displays
+fn focused_n_rows(app: &App) -> usize {
+    if let Some(Popup::Help(help)) = &app.popup {
+        return help.search.n_rows(help.rows.len());
+    }
+    match app.screen {
+        Screen::Queue => app.queue_state.search.n_rows(app.queue_state.group.len()),
+        Screen::Library => {
+            let library = &app.library_state;
+            match library.focused_part {
+                FocusedPart::Groups => library.search.n_rows(library.children.len()),
+                FocusedPart::Child(i) => library
+                    .children
+                    .get(i)
+                    .map_or(0, |child| child.search.n_rows(child.group.len())),
+            }
+        }
+        Screen::Files => {
+            let files = &app.files_state;
+            files.search.n_rows(files.dirs[files.current].len())
+        }
+        Screen::Search => {
+            let global_search = &app.global_search_state;
+            global_search.search.n_rows(global_search.songs.len())
+        }
+        _ => 0,
+    }
+}
+
+// keeps the selection of the focused list on one of its rows,
+// of which there can suddenly be fewer (e.g. when a search narrows them down)
+pub fn clamp_selection(app: &mut App) {
+    let n_rows = focused_n_rows(app);
+    if let Some((state, _)) = focused_list(app) {
+        match state.selected() {
+            Some(_) if n_rows == 0 => state.select(None),
+            Some(r) if r >= n_rows => state.select(Some(n_rows - 1)),
+            _ => (),
+        }
+    }
+}
+
+// the search of the list that the user is looking at
+fn focused_search(app: &mut App) -> Option<&mut Search> {
+    focused_list(app).map(|(_, search)| search)
+}
+
+// the list that the user is looking at, along with its search
+fn focused_list(app: &mut App) -> Option<(&mut TableState, &mut Search)> {
+    if let Some(Popup::Help(help)) = &mut app.popup {
+        return Some((&mut help.state, &mut help.search));
+    }
+    match app.screen {
+        Screen::Queue => Some((&mut app.queue_state.state, &mut app.queue_state.search)),
+        Screen::Library => {
+            let library = &mut app.library_state;
+            match library.focused_part {
+                FocusedPart::Groups => Some((&mut library.state, &mut library.search)),
+                FocusedPart::Child(i) => library
+                    .children
+                    .get_mut(i)
+                    .map(|child| (&mut child.state, &mut child.search)),
+            }
+        }
+        Screen::Files => Some((&mut app.files_state.state, &mut app.files_state.search)),
+        Screen::Search => Some((
+            &mut app.global_search_state.state,
+            &mut app.global_search_state.search,
+        )),
+        _ => None,
+    }
+}
+
+pub fn keybind_context(app: &App) -> KeybindContext {
+    match app.screen {
+        Screen::Cover => KeybindContext::Cover,
+        Screen::Queue => KeybindContext::Queue,
+        Screen::Library => KeybindContext::Library,
+        Screen::Files => KeybindContext::Files,
+        Screen::Stats => KeybindContext::Stats,
+        Screen::Search => KeybindContext::Search,
+    }
+}
+
+fn search_context(app: &App) -> Option<SearchContext> {
+    if let Some(Popup::Help(_)) = app.popup {
+        return Some(SearchContext::Help);
+    }
+    match app.screen {
+        Screen::Queue => Some(SearchContext::Queue),
+        Screen::Library => match app.library_state.focused_part {
+            FocusedPart::Groups => Some(SearchContext::LibraryGroups),
+            FocusedPart::Child(_) => Some(SearchContext::LibrarySongs),
+        },
+        Screen::Files => Some(SearchContext::Files),
+        Screen::Search => Some(SearchContext::Global),
+        _ => None,
+    }
+}
+
+pub fn search_is_pending(app: &mut App) -> bool {
+    focused_search(app).is_some_and(|search| search.is_pending())
+}
+
+fn search_is_idle(app: &mut App) -> bool {
+    focused_search(app).is_some_and(|search| matches!(search.state, SearchState::Idle))
+}
+
+// the song that the user is pointing at, falls back to the current song
+fn selected_song(app: &App) -> Option<String> {
+    let selected = match app.screen {
+        Screen::Queue => app
+            .queue_state
+            .unordered_selected()
+            .and_then(|i| app.musing_state.queue.get(i))
+            .map(|song| song.path.clone()),
+        Screen::Library => match app.library_state.focused_part {
+            FocusedPart::Child(i) => app.library_state.children.get(i).and_then(|child| {
+                child
+                    .unordered_selected()
+                    .and_then(|j| child.group.paths.get(j).cloned())
+            }),
+            FocusedPart::Groups => None,
+        },
+        Screen::Files => app.files_state.selected_file().map(|s| s.to_string()),
+        Screen::Search => app
+            .global_search_state
+            .selected_song()
+            .map(|s| s.to_string()),
+        _ => None,
+    };
+
+    selected.or_else(|| {
+        app.musing_state
+            .current
+            .and_then(|cur| app.musing_state.queue.get(cur as usize))
+            .map(|song| song.path.clone())
+    })
+}
+
 pub fn update_state(app: &mut App, delta: MusingStateDelta) {
-    if let Some(playback_state) = delta.playback_state {
-        app.musing_state.playback_state = playback_state;
+    // only the fields that actually changed cause a redraw
+    let state = &mut app.musing_state;
+    let mut dirty = false;
+    if let Some(playback_state) = delta.playback_state
+        && playback_state != state.playback_state
+    {
+        state.playback_state = playback_state;
+        dirty = true;
     }
-    if let Some(playback_mode) = delta.playback_mode {
-        app.musing_state.playback_mode = playback_mode;
+    if let Some(playback_mode) = delta.playback_mode
+        && playback_mode != state.playback_mode
+    {
+        state.playback_mode = playback_mode;
+        dirty = true;
     }
-    if let Some(volume) = delta.volume {
-        app.musing_state.volume = volume;
+    if let Some(volume) = delta.volume
+        && volume != state.volume
+    {
+        state.volume = volume;
+        // someone else changed the volume while muted, so there's nothing to restore anymore
+        // (a delta with the volume from before muting might have been sent before musing muted)
+        if volume != 0 && Some(volume) != app.unmuted_volume {
+            app.unmuted_volume = None;
+        }
+        dirty = true;
     }
-    if let Some(speed) = delta.speed {
-        app.musing_state.speed = speed;
+    if let Some(speed) = delta.speed
+        && speed != state.speed
+    {
+        state.speed = speed;
+        dirty = true;
     }
-    if let Some(gapless) = delta.gapless {
-        app.musing_state.gapless = gapless;
+    if let Some(gapless) = delta.gapless
+        && gapless != state.gapless
+    {
+        state.gapless = gapless;
+        dirty = true;
     }
-    if let Some(current) = delta.current {
-        app.musing_state.current = current;
+    if let Some(current) = delta.current
+        && current != state.current
+    {
+        state.current = current;
+        dirty = true;
     }
-    if let Some(cover_art) = delta.cover_art {
+    if let Some(cover_art) = delta.cover_art
+        && cover_art != state.cover_art
+    {
         let _ = app.cover_art_state.replace_art(cover_art.as_ref());
-        app.musing_state.cover_art = cover_art;
+        state.cover_art = cover_art;
+        dirty = true;
     }
-    if delta.timer.is_some() {
-        app.musing_state.timer = delta.timer;
+    if delta.timer.is_some() && delta.timer != state.timer {
+        state.timer = delta.timer;
+        dirty = true;
     }
-    if let Some(queue) = delta.queue {
+    app.dirty |= dirty;
+    if let Some(queue) = delta.queue
+        && queue != app.musing_state.queue
+    {
         app.musing_state.queue = queue;
+        app.dirty = true;
         update_queue(app);
     }
 }
 
+// plays the song that `play_now` added, the queue is up to date by now
+fn play_queued(app: &mut App) {
+    let Some(path) = app.play_when_queued.take() else {
+        return;
+    };
+    match app
+        .musing_state
+        .queue
+        .iter()
+        .rfind(|song| song.path == path)
+    {
+        Some(song) => app.connection.send(MusingRequest::Play(song.id)),
+        None => app.status_msg = Some(format!("couldn't add `{}` to the queue", path)),
+    }
+}
+
 pub fn update_queue(app: &mut App) {
     let paths: Vec<_> = app
         .musing_state
@@ -361,30 +1385,70 @@ pub fn update_queue(app: &mut App) {
         .map(|song| song.path.as_str().to_string())
         .collect();
     app.queue_state.group.paths = paths.clone();
+    app.queue_state.update_rows();
     app.connection.send(MusingRequest::Metadata(paths, None));
 }
 
 pub fn update_library(app: &mut App) {
     app.status_msg = Some("musing is updating...".into());
     app.connection.send(MusingRequest::Update);
+    app.library_state.clear_cache();
+    request_library_view(app);
+}
+
+pub fn request_library_view(app: &mut App) {
     app.connection.send(MusingRequest::GroupedSongs(
-        app.library_state.group_by_tags.to_vec(),
-        app.library_state.children_tags.to_vec(),
+        app.library_state.view().clone(),
+        app.library_state.fetched_tags(),
     ));
 }
 
 pub fn update_on_response(app: &mut App, response: MusingResponse) {
+    if !matches!(response, MusingResponse::StateDelta(_)) {
+        app.dirty = true;
+    }
     match response {
-        MusingResponse::Error(e) => app.status_msg = Some(format!("connection error: {}", e)),
+        MusingResponse::Error(e) => {
+            app.status_msg = Some(match app.macro_state.stop() {
+                Some(name) => format!("connection error: {} (macro `{}` stopped)", e, name),
+                None => format!("connection error: {}", e),
+            });
+        }
         MusingResponse::Metadata(meta) => {
-            app.queue_state.group.metadata = meta;
+            app.queue_state.group.metadata = meta.into_iter().map(Arc::new).collect();
+            app.queue_state.update_rows();
             app.queue_state
                 .search
-                .list_update(app.queue_state.metadata_to_repr());
+                .list_update(|| app.queue_state.metadata_to_items());
+        }
+        MusingResponse::GroupedSongs(view_name, grouped) => {
+            // every view has the same songs, so the other screens only need the current one
+            // (a view that arrives after switching away from it just gets cached)
+            let current = view_name == app.library_state.view().name;
+            app.library_state.update(view_name, grouped);
+            if !current {
+                return;
+            }
+            let children = &app.library_state.children;
+            app.files_state
+                .update(children.iter().flat_map(|child| child.group.paths.iter()));
+            app.stats_state.update(children);
+            app.global_search_state.update(children);
+        }
+        MusingResponse::SongInfo(path, tags) => {
+            app.popup = Some(Popup::SongInfo(SongInfoState::new(path, tags)))
         }
-        MusingResponse::GroupedSongs(grouped) => app.library_state.update(grouped),
         MusingResponse::StateDelta(delta) => update_state(app, delta),
         MusingResponse::Update(res) => app.status_msg = Some(res),
+        MusingResponse::Synced(delta) => {
+            if let Some(delta) = delta {
+                update_state(app, delta);
+            }
+            play_queued(app);
+            if app.macro_state.resume() {
+                run_macro(app);
+            }
+        }
     }
 }
 
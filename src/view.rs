@@ -2,17 +2,26 @@ use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Flex, Layout, Rect},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, Padding, Paragraph, Row, Table},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Cell, Clear, Padding, Paragraph, Row, Table,
+    },
 };
 use ratatui_image::{Resize, StatefulImage};
+use std::{mem, path::Path};
 
 use crate::{
-    app::{App, Screen},
+    app::{App, Popup, Screen},
     constants,
     model::{
-        common::FocusedPart,
+        common::{self, FocusedPart},
+        files::Entry,
+        help::HelpRow,
+        keybind,
+        mouse::Clickable,
         search::{Search, SearchState},
+        stats::Tally,
     },
+    update,
 };
 
 const SEARCH_PROMPT: &str = "> ";
@@ -56,7 +65,13 @@ fn render_header(app: &App, frame: &mut Frame, area: Rect) {
                         .centered()
                         .style(app.config.theme.current_title),
                 ),
-                Cell::from(Line::from(format!("Volume: {}", volume)).right_aligned()),
+                Cell::from(
+                    Line::from(match app.unmuted_volume {
+                        Some(unmuted) => format!("Muted ({})", unmuted),
+                        None => format!("Volume: {}", volume),
+                    })
+                    .right_aligned(),
+                ),
             ]),
             Row::new(vec![
                 Cell::from(Line::from(format!("[{}]", state)).left_aligned()),
@@ -83,7 +98,26 @@ fn render_header(app: &App, frame: &mut Frame, area: Rect) {
     frame.render_widget(header, area);
 }
 
-fn render_footer(app: &App, frame: &mut Frame, area: Rect) {
+fn render_footer(app: &mut App, frame: &mut Frame, area: Rect) {
+    if let Some(input) = app.command_line.input.as_ref() {
+        frame.set_cursor_position((area.x + input.visual_cursor() as u16 + 1, area.y));
+        frame.render_widget(Line::from(format!(":{}", input.value())), area);
+        return;
+    }
+    // the count typed so far goes to the right, like in vim
+    let area = match app.count {
+        Some(count) => {
+            let count = count.to_string();
+            let [area, count_area] = Layout::horizontal([
+                Constraint::Min(0),
+                Constraint::Length(count.len() as u16 + 1),
+            ])
+            .areas(area);
+            frame.render_widget(Line::from(count).right_aligned(), count_area);
+            area
+        }
+        None => area,
+    };
     let timer = app.musing_state.timer;
     let elapsed = timer.map(|timer| timer.0).unwrap_or_default();
     let duration = timer.map(|timer| timer.1).unwrap_or_default();
@@ -91,11 +125,17 @@ fn render_footer(app: &App, frame: &mut Frame, area: Rect) {
     let footer = match app.status_msg.as_deref() {
         Some(msg) => Line::from(msg),
         None => {
-            let timer_left = view_utils::format_time(elapsed);
-            let timer_right = view_utils::format_time(duration);
+            let timer_left = common::format_time(elapsed);
+            let timer_right = common::format_time(duration);
             let progress_bar_width = (area.width as usize) - 2 * (timer_left.len() + 1);
             let done_width =
                 (progress_bar_width as f32 * (elapsed as f32 / duration as f32)).round() as usize;
+            let progress_bar = Rect {
+                x: area.x + timer_left.len() as u16 + 1,
+                width: progress_bar_width as u16,
+                ..area
+            };
+            app.mouse_state.record(progress_bar, Clickable::ProgressBar);
 
             Line::from(vec![
                 Span::from(timer_left),
@@ -122,12 +162,62 @@ fn render_search_box(app: &App, frame: &mut Frame, area: Rect, search: &Search)
         Block::default()
             .borders(Borders::ALL)
             .border_style(app.config.theme.search_box)
-    };
+    }
+    .title(Line::from(search_mode(search)).left_aligned())
+    .title(
+        Line::from(match search.is_pending() {
+            true => "searching...".to_string(),
+            false => format!("{} matches", search.n_matches()),
+        })
+        .right_aligned(),
+    );
     let search_box =
         Paragraph::new(format!("{}{}", SEARCH_PROMPT, search.input.value())).block(search_block);
     frame.render_widget(search_box, area);
 }
 
+fn search_mode(search: &Search) -> String {
+    let mut mode = search.mode.to_string();
+    if search.case_sensitive {
+        mode += ", case-sensitive";
+    }
+    if !search.query.is_valid() {
+        mode += ", invalid pattern";
+    }
+
+    format!("[{}]", mode)
+}
+
+// `text` with the characters matched by the search pattern highlighted
+fn highlighted(app: &App, search: &Search, text: &str) -> Line<'static> {
+    let indices = match search.state {
+        SearchState::Off => Vec::new(),
+        _ => search.query.highlight(text),
+    };
+    if indices.is_empty() {
+        return Line::from(text.to_string());
+    }
+    let span = |s: String, matched: bool| match matched {
+        true => Span::styled(s, app.config.theme.search_highlight),
+        false => Span::raw(s),
+    };
+    let mut spans = Vec::new();
+    let mut indices = indices.into_iter().peekable();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let matched = indices.next_if_eq(&i).is_some();
+        if matched != run_matched && !run.is_empty() {
+            spans.push(span(mem::take(&mut run), run_matched));
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    spans.push(span(run, run_matched));
+
+    Line::from(spans)
+}
+
 fn render_cover_screen(app: &mut App, frame: &mut Frame) {
     let layout = Layout::default()
         .direction(Direction::Vertical)
@@ -160,52 +250,35 @@ fn render_cover_screen(app: &mut App, frame: &mut Frame) {
 }
 
 fn render_queue_screen(app: &mut App, frame: &mut Frame) {
-    let group = app.queue_state.ordered_group();
-    let displayed_data: &Vec<_> = &group
-        .metadata
-        .iter()
-        .zip(group.paths.iter())
-        .map(|(m, p)| {
-            app.queue_state
-                .queue_tags
-                .iter()
-                .map(|tag| match tag.as_str() {
-                    "tracktitle" => m.get(tag).map(|s| s.as_str()).unwrap_or(p).to_string(),
-                    _ => m
-                        .get(tag)
-                        .map(|s| s.as_str())
-                        .unwrap_or(constants::UNKNOWN)
-                        .to_string(),
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
-    let durations_int: Vec<_> = group
-        .metadata
-        .iter()
-        .map(|m| {
-            m.get("duration")
-                .map(|s| s.as_str().parse::<u64>().unwrap_or_default())
-                .unwrap_or_default()
-        })
-        .collect();
-    let total_duration = durations_int.iter().sum::<u64>();
-    let durations: Vec<_> = durations_int
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+    let (list_area, search_area) = view_utils::split_search(&app.queue_state.search, layout[1]);
+    let search = &app.queue_state.search;
+    let n_rows = search.n_rows(app.queue_state.rows.len());
+    let visible = view_utils::visible_rows(&mut app.queue_state.state, n_rows, list_area);
+    app.mouse_state.record(list_area, Clickable::Queue);
+    let mut state = view_utils::windowed(&app.queue_state.state, &visible);
+    let rows: Vec<_> = search
+        .real_range(visible)
         .into_iter()
-        .map(view_utils::format_time)
-        .collect();
-
-    let rows: Vec<_> = displayed_data
-        .iter()
-        .zip(durations)
-        .enumerate()
-        .map(|(i, t)| {
-            let mut v = t.0.clone();
-            v.push(t.1);
+        .filter_map(|i| app.queue_state.rows.get(i).map(|row| (i, row)))
+        .map(|(i, row)| {
+            let (duration, values) = row.split_last().unwrap();
+            let mut v: Vec<_> = values
+                .iter()
+                .map(|value| Cell::from(highlighted(app, search, value)))
+                .collect();
+            v.push(Cell::from(duration.as_str()));
             if app
                 .musing_state
                 .current
-                .is_some_and(|cur| cur == app.queue_state.search.real_i(i) as u64)
+                .is_some_and(|cur| cur as usize == i)
             {
                 Row::new(v).style(app.config.theme.selection_secondary)
             } else {
@@ -218,7 +291,7 @@ fn render_queue_screen(app: &mut App, frame: &mut Frame) {
         .title(
             Line::from(format!(
                 "Total duration: {}",
-                view_utils::format_time(total_duration)
+                common::format_time(app.queue_state.total_duration)
             ))
             .style(app.config.theme.total_duration),
         )
@@ -235,28 +308,10 @@ fn render_queue_screen(app: &mut App, frame: &mut Frame) {
         .block(block)
         .row_highlight_style(app.config.theme.selection_primary);
 
-    let layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(vec![
-            Constraint::Length(2),
-            Constraint::Fill(1),
-            Constraint::Length(1),
-        ])
-        .split(frame.area());
     render_header(app, frame, layout[0]);
-    let search = &app.queue_state.search;
-    match search.state {
-        SearchState::Off => {
-            frame.render_stateful_widget(list, layout[1], &mut app.queue_state.state)
-        }
-        _ => {
-            let sublayout = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints(vec![Constraint::Fill(1), Constraint::Length(3)])
-                .split(layout[1]);
-            frame.render_stateful_widget(list, sublayout[0], &mut app.queue_state.state);
-            render_search_box(app, frame, sublayout[1], search);
-        }
+    frame.render_stateful_widget(list, list_area, &mut state);
+    if let Some(area) = search_area {
+        render_search_box(app, frame, area, search);
     }
     render_footer(app, frame, layout[2]);
 }
@@ -272,44 +327,280 @@ fn render_library_screen(app: &mut App, frame: &mut Frame) {
             app.config.theme.selection_primary,
         ),
     };
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+    let middle = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Fill(1), Constraint::Fill(1)])
+        .split(layout[1]);
+    render_header(app, frame, layout[0]);
 
-    let children: Vec<_> = app
-        .library_state
-        .ordered_children()
-        .iter()
-        .map(|child| Row::new(child.id_comb.clone()))
+    let library_state = &mut app.library_state;
+    let (children_area, lhs_search_area) =
+        view_utils::split_search(&library_state.search, middle[0]);
+    let n_rows = library_state.search.n_rows(library_state.children.len());
+    let visible = view_utils::visible_rows(&mut library_state.state, n_rows, children_area);
+    let mut children_state = view_utils::windowed(&library_state.state, &visible);
+    app.mouse_state
+        .record(children_area, Clickable::LibraryGroups);
+    let lhs_search = &app.library_state.search;
+    let children: Vec<_> = lhs_search
+        .real_range(visible)
+        .into_iter()
+        .filter_map(|i| app.library_state.children.get(i))
+        .map(|child| {
+            Row::new(
+                child
+                    .id_comb
+                    .iter()
+                    .map(|value| Cell::from(highlighted(app, lhs_search, value))),
+            )
+        })
         .collect();
     let children_block = Block::default()
         .borders(Borders::ALL)
+        .title(format!("View: {}", app.library_state.view().name))
         .title_alignment(Alignment::Center)
         .padding(Padding::horizontal(1));
     let children_list = Table::default()
         .rows(children)
-        .widths(vec![Constraint::Fill(1), Constraint::Fill(1)])
+        .widths(vec![
+            Constraint::Fill(1);
+            app.library_state.view().group_by.len()
+        ])
         .block(children_block)
         .row_highlight_style(child_highlight);
+    frame.render_stateful_widget(children_list, children_area, &mut children_state);
+    if let Some(area) = lhs_search_area {
+        render_search_box(app, frame, area, lhs_search);
+    }
 
-    let songs = app
-        .library_state
-        .selected_child()
-        .map(|child| {
-            let group = child.ordered_group();
-            let mut titles = Vec::new();
-            for (meta, path) in group {
-                titles.push(meta.get("tracktitle").unwrap_or(path).to_string());
+    if let Some(child) = app.library_state.selected_child_mut() {
+        let (songs_area, rhs_search_area) = view_utils::split_search(&child.search, middle[1]);
+        let n_rows = child.search.n_rows(child.group.len());
+        let visible = view_utils::visible_rows(&mut child.state, n_rows, songs_area);
+        let mut songs_state = view_utils::windowed(&child.state, &visible);
+        app.mouse_state.record(songs_area, Clickable::LibrarySongs);
+        let child = app.library_state.selected_child().unwrap();
+        let rhs_search = &child.search;
+        let songs = rhs_search.real_range(visible).into_iter().filter_map(|i| {
+            let meta = child.group.metadata.get(i)?;
+            let path = child.group.paths.get(i)?;
+            let title = meta.get("tracktitle").unwrap_or(path);
+
+            Some(Row::new([highlighted(app, rhs_search, title)]))
+        });
+        let songs_block = Block::default()
+            .borders(Borders::ALL)
+            .title_alignment(Alignment::Center)
+            .padding(Padding::horizontal(1));
+        let song_list = Table::default()
+            .rows(songs)
+            .block(songs_block)
+            .row_highlight_style(song_highlight);
+        frame.render_stateful_widget(song_list, songs_area, &mut songs_state);
+        if let Some(area) = rhs_search_area {
+            render_search_box(app, frame, area, rhs_search);
+        }
+    } else {
+        let songs_block = Block::default()
+            .borders(Borders::ALL)
+            .padding(Padding::horizontal(1));
+        frame.render_widget(songs_block, middle[1]);
+    }
+    render_footer(app, frame, layout[2]);
+}
+
+fn render_files_screen(app: &mut App, frame: &mut Frame) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+    let files_state = &mut app.files_state;
+    let (list_area, search_area) = view_utils::split_search(&files_state.search, layout[1]);
+    let n_rows = files_state
+        .search
+        .n_rows(files_state.dirs[files_state.current].len());
+    let visible = view_utils::visible_rows(&mut files_state.state, n_rows, list_area);
+    app.mouse_state.record(list_area, Clickable::Files);
+    let mut state = view_utils::windowed(&files_state.state, &visible);
+    let search = &app.files_state.search;
+    let entries: Vec<_> = search
+        .real_range(visible)
+        .into_iter()
+        .filter_map(|i| app.files_state.entry(i))
+        .map(|entry| match entry {
+            Entry::Directory(dir) => {
+                let name = format!("{}/", dir.name);
+                Row::new([highlighted(app, search, &name)]).style(app.config.theme.directory)
             }
+            Entry::File(name) => Row::new([highlighted(app, search, name)]),
+        })
+        .collect();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(app.files_state.current_path())
+        .title_alignment(Alignment::Center)
+        .padding(Padding::horizontal(1));
+    let list = Table::default()
+        .rows(entries)
+        .widths(vec![Constraint::Fill(1)])
+        .block(block)
+        .row_highlight_style(app.config.theme.selection_primary);
+
+    render_header(app, frame, layout[0]);
+    frame.render_stateful_widget(list, list_area, &mut state);
+    if let Some(area) = search_area {
+        render_search_box(app, frame, area, search);
+    }
+    render_footer(app, frame, layout[2]);
+}
 
-            titles
+fn render_search_screen(app: &mut App, frame: &mut Frame) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .split(frame.area());
+    let global_search_state = &mut app.global_search_state;
+    let (list_area, search_area) = view_utils::split_search(&global_search_state.search, layout[1]);
+    let n_rows = global_search_state
+        .search
+        .n_rows(global_search_state.songs.len());
+    let visible = view_utils::visible_rows(&mut global_search_state.state, n_rows, list_area);
+    app.mouse_state.record(list_area, Clickable::Search);
+    let mut state = view_utils::windowed(&global_search_state.state, &visible);
+    let songs = &app.global_search_state.songs;
+    let search = &app.global_search_state.search;
+    let rows: Vec<_> = search
+        .real_range(visible)
+        .into_iter()
+        .filter_map(|i| Some((songs.metadata.get(i)?, songs.paths.get(i)?)))
+        .map(|(m, path)| {
+            let get = |tag: &str| m.get(tag).map(|s| s.as_str()).unwrap_or(constants::UNKNOWN);
+            Row::new(vec![
+                Cell::from(highlighted(
+                    app,
+                    search,
+                    m.get("tracktitle").unwrap_or(path),
+                )),
+                Cell::from(highlighted(app, search, get("artist")))
+                    .style(app.config.theme.current_artist),
+                Cell::from(highlighted(app, search, get("album")))
+                    .style(app.config.theme.current_album),
+            ])
         })
-        .unwrap_or_default();
-    let songs_block = Block::default()
+        .collect();
+    let block = Block::default()
         .borders(Borders::ALL)
+        .title(format!("All songs ({})", songs.len()))
         .title_alignment(Alignment::Center)
         .padding(Padding::horizontal(1));
-    let song_list = Table::default()
-        .rows(songs.into_iter().map(|song| Row::new([song])))
-        .block(songs_block)
-        .row_highlight_style(song_highlight);
+    let list = Table::default()
+        .rows(rows)
+        .widths(vec![Constraint::Fill(1); 3])
+        .block(block)
+        .row_highlight_style(app.config.theme.selection_primary);
+
+    render_header(app, frame, layout[0]);
+    frame.render_stateful_widget(list, list_area, &mut state);
+    if let Some(area) = search_area {
+        render_search_box(app, frame, area, search);
+    }
+    render_footer(app, frame, layout[2]);
+}
+
+fn render_tallies(app: &App, frame: &mut Frame, area: Rect, title: &str, tallies: &[Tally]) {
+    let rows: Vec<_> = tallies
+        .iter()
+        .take(area.height.saturating_sub(2) as usize)
+        .map(|tally| {
+            Row::new(vec![
+                Cell::from(tally.name.as_str()),
+                Cell::from(Line::from(tally.tracks.to_string()).right_aligned()),
+                Cell::from(Line::from(common::format_time(tally.duration)).right_aligned()),
+            ])
+        })
+        .collect();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Line::from(title).style(app.config.theme.tag_name))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::horizontal(1));
+    let table = Table::default()
+        .rows(rows)
+        .widths(vec![
+            Constraint::Fill(1),
+            Constraint::Length(6),
+            Constraint::Length(10),
+        ])
+        .block(block);
+    frame.render_widget(table, area);
+}
+
+fn render_stats_screen(app: &mut App, frame: &mut Frame) {
+    let stats = &app.stats_state.stats;
+    let totals = Table::default()
+        .rows(vec![
+            Row::new(vec![
+                Cell::from("Artists"),
+                Cell::from(stats.n_artists.to_string()),
+            ]),
+            Row::new(vec![
+                Cell::from("Albums"),
+                Cell::from(stats.n_albums.to_string()),
+            ]),
+            Row::new(vec![
+                Cell::from("Songs"),
+                Cell::from(stats.n_songs.to_string()),
+            ]),
+            Row::new(vec![
+                Cell::from("Playtime"),
+                Cell::from(common::format_time(stats.total_duration)),
+            ]),
+        ])
+        .widths(vec![Constraint::Length(10), Constraint::Fill(1)])
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Line::from("Totals").style(app.config.theme.tag_name))
+                .title_alignment(Alignment::Center)
+                .padding(Padding::horizontal(1)),
+        );
+    let bars: Vec<_> = stats
+        .decades
+        .iter()
+        .map(|(decade, n)| {
+            Bar::default()
+                .value(*n)
+                .label(Line::from(format!("{}s", decade)))
+        })
+        .collect();
+    let histogram = BarChart::default()
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(5)
+        .bar_gap(1)
+        .bar_style(app.config.theme.progress_bar_done)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Line::from("Songs per decade").style(app.config.theme.tag_name))
+                .title_alignment(Alignment::Center)
+                .padding(Padding::horizontal(1)),
+        );
 
     let layout = Layout::default()
         .direction(Direction::Vertical)
@@ -319,63 +610,272 @@ fn render_library_screen(app: &mut App, frame: &mut Frame) {
             Constraint::Length(1),
         ])
         .split(frame.area());
-    let middle = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(vec![Constraint::Fill(1), Constraint::Fill(1)])
-        .split(layout[1]);
+    let [lhs, rhs] =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Fill(2)]).areas(layout[1]);
+    let [totals_area, histogram_area] =
+        Layout::vertical([Constraint::Length(6), Constraint::Fill(1)]).areas(lhs);
+    let [top, bottom] = Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)]).areas(rhs);
+    let [genres_area, years_area] =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(top);
+    let [artists_area, albums_area] =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(bottom);
     render_header(app, frame, layout[0]);
-    let lhs_search = &app.library_state.search;
-    match lhs_search.state {
-        SearchState::Off => {
-            frame.render_stateful_widget(children_list, middle[0], &mut app.library_state.state)
-        }
-        _ => {
-            let sublayout = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints(vec![Constraint::Fill(1), Constraint::Length(3)])
-                .split(middle[0]);
-            frame.render_stateful_widget(children_list, sublayout[0], &mut app.library_state.state);
-            render_search_box(app, frame, sublayout[1], lhs_search);
+    frame.render_widget(totals, totals_area);
+    frame.render_widget(histogram, histogram_area);
+    let order = if app.stats_state.by_duration {
+        "by duration"
+    } else {
+        "by tracks"
+    };
+    let stats = &app.stats_state.stats;
+    render_tallies(
+        app,
+        frame,
+        genres_area,
+        &format!("Top genres ({})", order),
+        &stats.genres,
+    );
+    render_tallies(
+        app,
+        frame,
+        years_area,
+        &format!("Top years ({})", order),
+        &stats.years,
+    );
+    render_tallies(
+        app,
+        frame,
+        artists_area,
+        &format!("Top artists ({})", order),
+        &stats.artists,
+    );
+    render_tallies(
+        app,
+        frame,
+        albums_area,
+        &format!("Top albums ({})", order),
+        &stats.albums,
+    );
+    render_footer(app, frame, layout[2]);
+}
+
+fn render_popup(app: &mut App, frame: &mut Frame) {
+    let area = view_utils::centered(frame.area(), 70, 70);
+    frame.render_widget(Clear, area);
+    match &mut app.popup {
+        Some(Popup::SongInfo(info)) => {
+            let title = Path::new(&info.path)
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let rows: Vec<_> = info
+                .rows
+                .iter()
+                .map(|(tag, value)| {
+                    Row::new(vec![
+                        Cell::from(tag.as_str()).style(app.config.theme.tag_name),
+                        Cell::from(value.as_str()),
+                    ])
+                })
+                .collect();
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_alignment(Alignment::Center)
+                .padding(Padding::horizontal(1));
+            let table = Table::default()
+                .rows(rows)
+                .widths(vec![Constraint::Length(20), Constraint::Fill(1)])
+                .block(block)
+                .row_highlight_style(app.config.theme.selection_primary);
+            frame.render_stateful_widget(table, area, &mut info.state);
         }
+        Some(Popup::Help(_)) => render_help(app, frame, area),
+        None => (),
     }
-    if let Some(child) = app.library_state.selected_child() {
-        let rhs_search = &child.search;
-        match rhs_search.state {
-            SearchState::Off => {
-                let state = &mut app.library_state.selected_child_mut().unwrap().state;
-                frame.render_stateful_widget(song_list, middle[1], state);
-            }
-            _ => {
-                let sublayout = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints(vec![Constraint::Fill(1), Constraint::Length(3)])
-                    .split(middle[1]);
-                render_search_box(app, frame, sublayout[1], rhs_search);
-                let state = &mut app.library_state.selected_child_mut().unwrap().state;
-                frame.render_stateful_widget(song_list, sublayout[0], state);
-            }
-        }
+}
+
+fn render_help(app: &mut App, frame: &mut Frame, area: Rect) {
+    let Some(Popup::Help(help)) = &mut app.popup else {
+        return;
+    };
+    let (list_area, search_area) = view_utils::split_search(&help.search, area);
+    let n_rows = help.search.n_rows(help.rows.len());
+    let visible = view_utils::visible_rows(&mut help.state, n_rows, list_area);
+    let mut state = view_utils::windowed(&help.state, &visible);
+    let Some(Popup::Help(help)) = &app.popup else {
+        return;
+    };
+    let search = &help.search;
+    let width = |column: fn(&HelpRow) -> &str| {
+        help.rows
+            .iter()
+            .map(|row| column(row).chars().count())
+            .max()
+            .unwrap_or_default() as u16
+    };
+    let rows: Vec<_> = search
+        .real_range(visible)
+        .into_iter()
+        .filter_map(|i| help.rows.get(i))
+        .map(|row| {
+            Row::new(vec![
+                Cell::from(row.context.as_str()).style(app.config.theme.tag_name),
+                Cell::from(highlighted(app, search, &row.keys)),
+                Cell::from(highlighted(app, search, &row.action)),
+                Cell::from(highlighted(app, search, row.description)),
+            ])
+        })
+        .collect();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Help")
+        .title_alignment(Alignment::Center)
+        .padding(Padding::horizontal(1));
+    let table = Table::default()
+        .rows(rows)
+        .widths(vec![
+            Constraint::Length(width(|row| &row.context)),
+            Constraint::Length(width(|row| &row.keys).min(24)),
+            Constraint::Length(width(|row| &row.action).min(24)),
+            Constraint::Fill(1),
+        ])
+        .block(block)
+        .row_highlight_style(app.config.theme.selection_primary);
+    frame.render_stateful_widget(table, list_area, &mut state);
+    if let Some(area) = search_area {
+        render_search_box(app, frame, area, search);
     }
-    render_footer(app, frame, layout[2]);
+}
+
+// the keys that can complete a half-typed sequence, in the bottom right corner
+fn render_which_key(app: &App, frame: &mut Frame) {
+    let context = update::keybind_context(app);
+    let continuations = app.config.keybind.continuations(context, &app.key_events);
+    if continuations.is_empty() {
+        return;
+    }
+    let key_width = continuations
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or_default();
+    let lines: Vec<_> = continuations
+        .iter()
+        .map(|(key, description)| {
+            Line::from(vec![
+                Span::from(format!("{:<width$}  ", key, width = key_width))
+                    .style(app.config.theme.tag_name),
+                Span::from(description.as_str()),
+            ])
+        })
+        .collect();
+    // above the footer
+    let frame_area = frame.area();
+    let max_height = frame_area.height.saturating_sub(1);
+    let width = lines
+        .iter()
+        .map(|line| line.width())
+        .max()
+        .unwrap_or_default() as u16
+        + 4;
+    let height = (lines.len() as u16 + 2).min(max_height);
+    let width = width.min(frame_area.width);
+    let area = Rect {
+        x: frame_area.right() - width,
+        y: frame_area.y + max_height - height,
+        width,
+        height,
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(keybind::key_sequence_to_string(&app.key_events))
+        .padding(Padding::horizontal(1));
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
 }
 
 pub fn render(app: &mut App, frame: &mut Frame) {
+    app.mouse_state.clear();
     match app.screen {
         Screen::Cover => render_cover_screen(app, frame),
         Screen::Queue => render_queue_screen(app, frame),
         Screen::Library => render_library_screen(app, frame),
+        Screen::Files => render_files_screen(app, frame),
+        Screen::Stats => render_stats_screen(app, frame),
+        Screen::Search => render_search_screen(app, frame),
+    }
+    if app.popup.is_some() {
+        render_popup(app, frame);
+    }
+    if !app.key_events.is_empty() {
+        render_which_key(app, frame);
     }
 }
 
 pub mod view_utils {
-    pub fn format_time(secs: u64) -> String {
-        let h = secs / 3600;
-        let m = (secs - h * 3600) / 60;
-        let s = secs - h * 3600 - m * 60;
-        if h == 0 {
-            format!("{:02}:{:02}", m, s)
-        } else {
-            format!("{:02}:{:02}:{:02}", h, m, s)
+    use ratatui::{
+        layout::{Constraint, Direction, Flex, Layout, Rect},
+        widgets::TableState,
+    };
+    use std::ops::Range;
+
+    use crate::model::search::{Search, SearchState};
+
+    // a rectangle of the given size (in percents) in the middle of `area`
+    pub fn centered(area: Rect, width: u16, height: u16) -> Rect {
+        let [area] = Layout::vertical([Constraint::Percentage(height)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::horizontal([Constraint::Percentage(width)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        area
+    }
+
+    // the area of a list and of its search box (if the search is on)
+    pub fn split_search(search: &Search, area: Rect) -> (Rect, Option<Rect>) {
+        match search.state {
+            SearchState::Off => (area, None),
+            _ => {
+                let sublayout = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(vec![Constraint::Fill(1), Constraint::Length(3)])
+                    .split(area);
+
+                (sublayout[0], Some(sublayout[1]))
+            }
         }
     }
+
+    // the rows of a bordered table that fit in `area`,
+    // the table's offset is moved so that the selected row stays visible
+    pub fn visible_rows(state: &mut TableState, n_rows: usize, area: Rect) -> Range<usize> {
+        let height = area.height.saturating_sub(2) as usize;
+        if height == 0 {
+            return 0..0;
+        }
+        let mut offset = state.offset();
+        if let Some(selected) = state.selected().map(|i| i.min(n_rows.saturating_sub(1))) {
+            if selected < offset {
+                offset = selected;
+            } else if selected >= offset + height {
+                offset = selected + 1 - height;
+            }
+        }
+        // don't leave empty space at the bottom when the list shrinks
+        offset = offset.min(n_rows.saturating_sub(height));
+        *state.offset_mut() = offset;
+
+        offset..(offset + height).min(n_rows)
+    }
+
+    // the state of a table made of only the `visible` rows
+    pub fn windowed(state: &TableState, visible: &Range<usize>) -> TableState {
+        TableState::default().with_selected(state.selected().map(|i| {
+            i.saturating_sub(visible.start)
+                .min(visible.len().saturating_sub(1))
+        }))
+    }
 }